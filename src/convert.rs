@@ -0,0 +1,579 @@
+//! Conversion of values into Rust types.
+//!
+//! ```
+//! let cfg = ucl::parse("general { fork = four; }").unwrap();
+//! assert_eq!(cfg.try_get::<String>("general.fork").unwrap(), "four");
+//!
+//! let err = cfg.try_get::<i64>("general.fork").unwrap_err();
+//! assert_eq!((err.expected, err.found), ("integer", "string"));
+//! assert_eq!(err.to_string(), "`general.fork`: expected integer, found string");
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{TryFrom, TryInto};
+use std::error;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::iter::FromIterator;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use indexmap::IndexMap;
+
+use super::Value;
+use super::path::{push_index, push_key};
+
+/// Conversion from a `Value`.
+pub trait FromUcl where Self: Sized {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError>;
+}
+
+/// A value that could not be converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertError {
+    /// What the conversion needed.
+    pub expected: &'static str,
+    /// The type of the value found, or `nothing` where there was no value.
+    pub found: &'static str,
+    /// The value itself, where its type was right but its contents were not,
+    /// as for a string that is not an address.
+    pub value: Option<String>,
+    /// Where the value is, in the form taken by `Spans`. Empty for the value
+    /// converted itself.
+    pub path: String,
+}
+
+impl ConvertError {
+    /// Creates an error for converting `found` into something it is not.
+    pub fn new(expected: &'static str, found: &Value) -> ConvertError {
+        ConvertError { expected, found: found.type_name(), value: None, path: String::new() }
+    }
+
+    /// Creates an error for a string `s` that cannot be read as `expected`.
+    pub fn invalid(expected: &'static str, s: &str) -> ConvertError {
+        ConvertError { expected, found: "string", value: Some(s.to_owned()), path: String::new() }
+    }
+
+    /// Places the error below the object key `key`. Conversions of
+    /// containers call this on the errors of their items.
+    pub fn in_key(self, key: &str) -> ConvertError {
+        let mut path = String::new();
+        push_key(&mut path, key);
+        self.within(&path)
+    }
+
+    /// Places the error below the array index `idx`.
+    pub fn in_index(self, idx: usize) -> ConvertError {
+        let mut path = String::new();
+        push_index(&mut path, idx);
+        self.within(&path)
+    }
+
+    /// Places the error below `path`.
+    pub(crate) fn within(mut self, path: &str) -> ConvertError {
+        if self.path.is_empty() || path.is_empty() {
+            self.path.insert_str(0, path);
+        } else if self.path.starts_with('[') {
+            self.path = format!("{}{}", path, self.path);
+        } else {
+            self.path = format!("{}.{}", path, self.path);
+        }
+        self
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "`{}`: ", self.path)?;
+        }
+        write!(f, "expected {}, found {}", self.expected, self.found)?;
+        if let Some(ref value) = self.value {
+            write!(f, " `{}`", value)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConvertError {}
+
+impl FromUcl for Value {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        Ok(v.clone())
+    }
+}
+
+macro_rules! from_integer {
+    ($($t:ident)*) => {$(
+        impl FromUcl for $t {
+            fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+                match *v {
+                    Value::Number(n) | Value::Size(n) => $t::try_from(n).map_err(|_| ConvertError {
+                        expected: stringify!($t),
+                        found: "out-of-range integer",
+                        value: None,
+                        path: String::new(),
+                    }),
+                    _ => Err(ConvertError::new(stringify!($t), v)),
+                }
+            }
+        }
+    )*}
+}
+
+from_integer!(i8 i16 i32 i128 isize u8 u16 u32 u64 u128 usize);
+
+impl FromUcl for i64 {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Number(n) | Value::Size(n) => Ok(n),
+            _ => Err(ConvertError::new("integer", v)),
+        }
+    }
+}
+
+impl FromUcl for f64 {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Float(n) | Value::Time(n) => Ok(n),
+            _ => Err(ConvertError::new("float", v)),
+        }
+    }
+}
+
+impl FromUcl for f32 {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Float(n) | Value::Time(n) if n.is_finite() && (n as f32).is_infinite() => Err(ConvertError {
+                expected: "f32",
+                found: "out-of-range float",
+                value: None,
+                path: String::new(),
+            }),
+            Value::Float(n) | Value::Time(n) => Ok(n as f32),
+            _ => Err(ConvertError::new("f32", v)),
+        }
+    }
+}
+
+/// Plain numbers are taken as seconds.
+impl FromUcl for Duration {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        let secs = match *v {
+            Value::Time(n) | Value::Float(n) => n,
+            Value::Number(n) => n as f64,
+            _ => return Err(ConvertError::new("duration", v)),
+        };
+        Duration::try_from_secs_f64(secs).map_err(|_| ConvertError {
+            expected: "duration",
+            found: "negative or out-of-range number",
+            value: None,
+            path: String::new(),
+        })
+    }
+}
+
+/// A number of bytes, read from a size such as `25mb` or a plain integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteSize(pub u64);
+
+impl FromUcl for ByteSize {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Size(n) | Value::Number(n) => u64::try_from(n).map(ByteSize).map_err(|_| ConvertError {
+                expected: "byte size",
+                found: "negative number",
+                value: None,
+                path: String::new(),
+            }),
+            _ => Err(ConvertError::new("byte size", v)),
+        }
+    }
+}
+
+impl FromUcl for String {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::String(ref s) => Ok(s.clone()),
+            _ => Err(ConvertError::new("string", v)),
+        }
+    }
+}
+
+impl FromUcl for bool {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(ConvertError::new("boolean", v)),
+        }
+    }
+}
+
+impl FromUcl for PathBuf {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::String(ref s) => Ok(PathBuf::from(s)),
+            _ => Err(ConvertError::new("path", v)),
+        }
+    }
+}
+
+fn parse_string<T: FromStr>(v: &Value, expected: &'static str) -> Result<T, ConvertError> {
+    match *v {
+        Value::String(ref s) => s.parse().map_err(|_| ConvertError::invalid(expected, s)),
+        _ => Err(ConvertError::new(expected, v)),
+    }
+}
+
+impl FromUcl for IpAddr {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        parse_string(v, "IP address")
+    }
+}
+
+impl FromUcl for Ipv4Addr {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        parse_string(v, "IPv4 address")
+    }
+}
+
+impl FromUcl for Ipv6Addr {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        parse_string(v, "IPv6 address")
+    }
+}
+
+/// Besides `address:port`, a port alone is taken as the port on all
+/// interfaces, whether written `:80` or `80`.
+impl FromUcl for SocketAddr {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        let any = |port| SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port);
+        match *v {
+            Value::String(ref s) if s.starts_with(':') => s[1..].parse().map(any)
+                .map_err(|_| ConvertError::invalid("socket address", s)),
+            Value::Number(n) => u16::try_from(n).map(any).map_err(|_| ConvertError {
+                expected: "socket address",
+                found: "out-of-range port",
+                value: Some(n.to_string()),
+                path: String::new(),
+            }),
+            _ => parse_string(v, "socket address"),
+        }
+    }
+}
+
+/// A network in CIDR notation such as `10.0.0.0/8` or `fd00::/8`. An
+/// address without a prefix length is a network of that address alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IpNet {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl IpNet {
+    /// Tells whether `addr` is within the network.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let (net, addr, bits) = match (self.addr, *addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => (u32::from(net) as u128, u32::from(addr) as u128, 32),
+            (IpAddr::V6(net), IpAddr::V6(addr)) => (u128::from(net), u128::from(addr), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix_len);
+        shift >= bits || net >> shift == addr >> shift
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.find('/') {
+            Some(i) => (s[..i].parse::<IpAddr>().map_err(|_| ())?, Some(&s[i + 1..])),
+            None => (s.parse::<IpAddr>().map_err(|_| ())?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(n) if n.bytes().all(|b| b.is_ascii_digit()) => n.parse().map_err(|_| ())?,
+            Some(_) => return Err(()),
+            None => max,
+        };
+        if prefix_len > max {
+            return Err(());
+        }
+        Ok(IpNet { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromUcl for IpNet {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        parse_string(v, "IP network")
+    }
+}
+
+/// A host name or address with a port, as in `db.example.com:5432` or
+/// `[::1]:8080`. A leading scheme such as `tcp://` and a trailing `/` are
+/// ignored, so URLs without a path are also accepted.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HostPort {
+    /// The host, without the brackets around an IPv6 address.
+    pub host: String,
+    pub port: u16,
+}
+
+impl FromStr for HostPort {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.find("://").map_or(s, |i| &s[i + 3..]);
+        let s = s.strip_suffix('/').unwrap_or(s);
+        let i = s.rfind(':').ok_or(())?;
+        let (host, port) = (&s[..i], &s[i + 1..]);
+        let host = if host.starts_with('[') && host.ends_with(']') {
+            let host = &host[1..host.len() - 1];
+            host.parse::<Ipv6Addr>().map_err(|_| ())?;
+            host
+        } else if host.is_empty() || host.contains(|c: char| "[]/:@ ".contains(c) || c.is_control()) {
+            return Err(());
+        } else {
+            host
+        };
+        if !port.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+        Ok(HostPort { host: host.to_owned(), port: port.parse().map_err(|_| ())? })
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl FromUcl for HostPort {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        parse_string(v, "host and port")
+    }
+}
+
+/// `null` is `None`.
+impl<T: FromUcl> FromUcl for Option<T> {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Null => Ok(None),
+            _ => T::from_ucl(v).map(Some),
+        }
+    }
+}
+
+/// Anything but an array is taken as an array of one item, as a key given
+/// once is the same as an implicit array of one value.
+impl<T: FromUcl> FromUcl for Vec<T> {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Array(ref items) => items.iter().enumerate()
+                .map(|(i, v)| T::from_ucl(v).map_err(|err| err.in_index(i)))
+                .collect(),
+            _ => T::from_ucl(v).map(|v| vec![v]),
+        }
+    }
+}
+
+impl<T: FromUcl, const N: usize> FromUcl for [T; N] {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        Vec::<T>::from_ucl(v)?.try_into().map_err(|_| ConvertError::new("array of the right length", v))
+    }
+}
+
+fn from_object<T: FromUcl, C: FromIterator<(String, T)>>(v: &Value) -> Result<C, ConvertError> {
+    match *v {
+        Value::Object(ref obj) => obj.iter()
+            .map(|(k, v)| T::from_ucl(v).map(|v| (k.clone(), v)).map_err(|err| err.in_key(k)))
+            .collect(),
+        _ => Err(ConvertError::new("object", v)),
+    }
+}
+
+impl<T: FromUcl, S: BuildHasher + Default> FromUcl for IndexMap<String, T, S> {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        from_object(v)
+    }
+}
+
+impl<T: FromUcl, S: BuildHasher + Default> FromUcl for HashMap<String, T, S> {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        from_object(v)
+    }
+}
+
+impl<T: FromUcl> FromUcl for BTreeMap<String, T> {
+    fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+        from_object(v)
+    }
+}
+
+macro_rules! from_tuple {
+    ($($len:tt => ($($t:ident $i:tt)*))*) => {$(
+        impl<$($t: FromUcl),*> FromUcl for ($($t,)*) {
+            fn from_ucl(v: &Value) -> Result<Self, ConvertError> {
+                match *v {
+                    Value::Array(ref items) if items.len() == $len => Ok((
+                        $($t::from_ucl(&items[$i]).map_err(|err| err.in_index($i))?,)*
+                    )),
+                    _ => Err(ConvertError::new(concat!("array of ", $len), v)),
+                }
+            }
+        }
+    )*}
+}
+
+from_tuple! {
+    1 => (A 0)
+    2 => (A 0 B 1)
+    3 => (A 0 B 1 C 2)
+    4 => (A 0 B 1 C 2 D 3)
+    5 => (A 0 B 1 C 2 D 3 E 4)
+    6 => (A 0 B 1 C 2 D 3 E 4 F 5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_error_path() {
+        let err = ConvertError::new("integer", &Value::Null);
+        assert_eq!(err.to_string(), "expected integer, found null");
+        let err = err.in_key("port").in_index(1).in_key("www.example.com").in_key("site");
+        assert_eq!(err.path, r#"site."www.example.com"[1].port"#);
+        assert_eq!(err.to_string(), r#"`site."www.example.com"[1].port`: expected integer, found null"#);
+    }
+
+    #[test]
+    fn test_from_ucl() {
+        let v = ::parse(r#"
+            port = 8080;
+            big = 70000;
+            ratio = 0.5;
+            hosts = [a, b];
+            host = a;
+            backends { a = 1; b = 2; }
+            pair = [x, 1];
+            nothing = null;
+        "#).unwrap();
+
+        assert_eq!(v.try_get::<u16>("port"), Ok(8080));
+        assert_eq!(v.try_get::<usize>("port"), Ok(8080));
+        assert_eq!(v.try_get::<i8>("port").unwrap_err().to_string(), "`port`: expected i8, found out-of-range integer");
+        assert_eq!(v.try_get::<u64>("ratio").unwrap_err().to_string(), "`ratio`: expected u64, found float");
+        assert_eq!(v.try_get::<f32>("ratio"), Ok(0.5));
+        assert_eq!(v.try_get::<f32>("port").unwrap_err().found, "integer");
+        assert_eq!(f32::from_ucl(&Value::from(1e300)).unwrap_err().found, "out-of-range float");
+
+        assert_eq!(v.try_get::<Option<u16>>("port"), Ok(Some(8080)));
+        assert_eq!(v.try_get::<Option<u16>>("nothing"), Ok(None));
+        assert_eq!(v.try_get::<Option<u16>>("missing"), Ok(None));
+
+        assert_eq!(v.try_get::<Vec<String>>("hosts"), Ok(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(v.try_get::<Vec<String>>("host"), Ok(vec!["a".to_owned()]));
+        assert_eq!(v.try_get::<Vec<u16>>("hosts").unwrap_err().path, "hosts[0]");
+        assert_eq!(v.try_get::<[String; 2]>("hosts"), Ok(["a".to_owned(), "b".to_owned()]));
+        assert!(v.try_get::<[String; 3]>("hosts").is_err());
+
+        let backends: HashMap<String, u8> = v.try_get("backends").unwrap();
+        assert_eq!(backends["b"], 2);
+        let backends: BTreeMap<String, u8> = v.try_get("backends").unwrap();
+        assert_eq!(backends.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(v.try_get::<IndexMap<String, bool>>("backends").unwrap_err().path, "backends.a");
+
+        assert_eq!(v.try_get::<(String, u32)>("pair"), Ok(("x".to_owned(), 1)));
+        assert_eq!(v.try_get::<(String, String)>("pair").unwrap_err().path, "pair[1]");
+        assert_eq!(v.try_get::<(String,)>("pair").unwrap_err().expected, "array of 1");
+    }
+
+    #[test]
+    fn test_duration_byte_size() {
+        let v = ::parse("timeout = 1h30min; poll = 100ms; retry = 5; backoff = 0.5; past = -1s; buffer = 25mb; limit = 4096; neg = -1kb;").unwrap();
+        assert_eq!(v.try_get::<Duration>("timeout"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(v.try_get::<Duration>("poll"), Ok(Duration::from_millis(100)));
+        assert_eq!(v.try_get::<Duration>("retry"), Ok(Duration::from_secs(5)));
+        assert_eq!(v.try_get::<Duration>("backoff"), Ok(Duration::from_millis(500)));
+        assert_eq!(v.try_get::<Duration>("past").unwrap_err().found, "negative or out-of-range number");
+        assert_eq!(v.try_get::<Duration>("buffer").unwrap_err().to_string(), "`buffer`: expected duration, found size");
+        assert_eq!(v.try_get::<f64>("poll"), Ok(0.1));
+
+        assert_eq!(v.try_get::<ByteSize>("buffer"), Ok(ByteSize(25 << 20)));
+        assert_eq!(v.try_get::<ByteSize>("limit"), Ok(ByteSize(4096)));
+        assert_eq!(v.try_get::<ByteSize>("neg").unwrap_err().found, "negative number");
+        assert_eq!(v.try_get::<ByteSize>("poll").unwrap_err().found, "time");
+        assert_eq!(v.try_get::<u32>("buffer"), Ok(25 << 20));
+
+        assert_eq!(Value::from(Duration::from_millis(1500)), Value::Time(1.5));
+        assert_eq!(Value::from(ByteSize(2048)), Value::Size(2048));
+    }
+
+    #[test]
+    fn test_net_path() {
+        let v = ::parse(r#"
+            bind = [":80", "127.0.0.1:8080", "[::1]:443", 8000];
+            bad_bind = ":8o";
+            far_port = 70000;
+            ip = "10.1.2.3";
+            v6 = "fe80::1";
+            net = "10.0.0.0/8";
+            nets = ["fd00::/8", "192.168.1.1", "10.0.0.0/33"];
+            upstream = ["db.example.com:5432", "tcp://[::1]:6379/", "localhost"];
+            base_dir = "/var/www";
+        "#).unwrap();
+
+        let bind: Vec<SocketAddr> = v.try_get("bind").unwrap();
+        assert_eq!(bind, ["0.0.0.0:80", "127.0.0.1:8080", "[::1]:443", "0.0.0.0:8000"].iter()
+                   .map(|s| s.parse().unwrap()).collect::<Vec<SocketAddr>>());
+        let err = v.try_get::<SocketAddr>("bad_bind").unwrap_err();
+        assert_eq!(err.value.as_deref(), Some(":8o"));
+        assert_eq!(err.to_string(), "`bad_bind`: expected socket address, found string `:8o`");
+        assert_eq!(v.try_get::<SocketAddr>("far_port").unwrap_err().to_string(),
+                   "`far_port`: expected socket address, found out-of-range port `70000`");
+
+        assert_eq!(v.try_get::<IpAddr>("ip"), Ok(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))));
+        assert_eq!(v.try_get::<Ipv4Addr>("ip"), Ok(Ipv4Addr::new(10, 1, 2, 3)));
+        assert_eq!(v.try_get::<Ipv6Addr>("v6"), Ok("fe80::1".parse().unwrap()));
+        assert_eq!(v.try_get::<Ipv6Addr>("ip").unwrap_err().to_string(), "`ip`: expected IPv6 address, found string `10.1.2.3`");
+        assert_eq!(v.try_get::<IpAddr>("bind").unwrap_err().to_string(), "`bind`: expected IP address, found array");
+
+        let net: IpNet = v.try_get("net").unwrap();
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+        assert!(net.contains(&"10.200.0.1".parse().unwrap()));
+        assert!(!net.contains(&"11.0.0.1".parse().unwrap()));
+        assert!(!net.contains(&"::1".parse().unwrap()));
+        let err = v.try_get::<Vec<IpNet>>("nets").unwrap_err();
+        assert_eq!(err.to_string(), "`nets[2]`: expected IP network, found string `10.0.0.0/33`");
+        assert_eq!(v.try_get::<IpNet>("nets[1]"), Ok(IpNet { addr: "192.168.1.1".parse().unwrap(), prefix_len: 32 }));
+        assert!(IpNet { addr: "::".parse().unwrap(), prefix_len: 0 }.contains(&"fd00::1".parse().unwrap()));
+
+        assert_eq!(v.try_get::<HostPort>("upstream[0]"), Ok(HostPort { host: "db.example.com".to_owned(), port: 5432 }));
+        let hp: HostPort = v.try_get("upstream[1]").unwrap();
+        assert_eq!((hp.host.as_str(), hp.port), ("::1", 6379));
+        assert_eq!(hp.to_string(), "[::1]:6379");
+        assert_eq!(v.try_get::<HostPort>("upstream[2]").unwrap_err().to_string(),
+                   "`upstream[2]`: expected host and port, found string `localhost`");
+        for s in &[":80", "host:", "host:99999", "a b:1", "[nothost]:1", "::1:80"] {
+            assert!(s.parse::<HostPort>().is_err(), "{}", s);
+        }
+
+        assert_eq!(v.try_get::<PathBuf>("base_dir"), Ok(PathBuf::from("/var/www")));
+        assert_eq!(v.try_get::<PathBuf>("far_port").unwrap_err().expected, "path");
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::path::Path;
+use std::slice;
+
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer,
+                MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::de::value::BorrowedStrDeserializer;
+
+use super::{parse, parse_file, Object, UclError, Value};
+
+impl de::Error for UclError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        UclError::Message(msg.to_string())
+    }
+}
+
+/// Deserializes an instance of `T` from a UCL document.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, UclError> {
+    T::deserialize(&parse(s)?)
+}
+
+/// Deserializes an instance of `T` from a UCL file, expanding `.include`
+/// macros relative to the file.
+pub fn from_file<T: DeserializeOwned, P: AsRef<Path> + ?Sized>(filename: &P) -> Result<T, UclError> {
+    T::deserialize(&parse_file(filename)?)
+}
+
+/// Deserializes an instance of `T` from a parsed `Value`.
+pub fn from_value<'de, T: Deserialize<'de>>(value: &'de Value) -> Result<T, UclError> {
+    T::deserialize(value)
+}
+
+impl<'de> de::Deserializer<'de> for &'de Value {
+    type Error = UclError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, UclError> {
+        match *self {
+            Value::Number(n) => visitor.visit_i64(n),
+            Value::Float(n) | Value::Time(n) => visitor.visit_f64(n),
+            Value::Size(n) => visitor.visit_i64(n),
+            Value::String(ref s) => visitor.visit_borrowed_str(s),
+            Value::Boolean(b) => visitor.visit_bool(b),
+            Value::Array(ref items) => visitor.visit_seq(SeqDeserializer::new(items)),
+            Value::Object(ref obj) => visitor.visit_map(MapDeserializer::new(obj)),
+            Value::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, UclError> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, UclError> {
+        match *self {
+            Value::Number(n) => visitor.visit_f64(n as f64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, UclError> {
+        match *self {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+                                                   -> Result<V::Value, UclError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // A key that appears once is a scalar, while a repeated key becomes an
+    // implicit array, so a lone value is accepted as a one-element sequence.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, UclError> {
+        match *self {
+            Value::Array(ref items) => visitor.visit_seq(SeqDeserializer::new(items)),
+            _ => visitor.visit_seq(SeqDeserializer::new(slice::from_ref(self))),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value, UclError> {
+        match *self {
+            Value::String(ref s) => visitor.visit_enum(EnumDeserializer { variant: s, value: None }),
+            Value::Object(ref obj) if obj.len() == 1 => {
+                let (variant, value) = obj.iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer { variant, value: Some(value) })
+            },
+            _ => Err(de::Error::invalid_type(unexpected(self), &"string or single-key object")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf
+        unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn unexpected(v: &Value) -> de::Unexpected<'_> {
+    match *v {
+        Value::Number(n) => de::Unexpected::Signed(n),
+        Value::Float(n) | Value::Time(n) => de::Unexpected::Float(n),
+        Value::Size(n) => de::Unexpected::Signed(n),
+        Value::String(ref s) => de::Unexpected::Str(s),
+        Value::Boolean(b) => de::Unexpected::Bool(b),
+        Value::Array(_) => de::Unexpected::Seq,
+        Value::Object(_) => de::Unexpected::Map,
+        Value::Null => de::Unexpected::Unit,
+    }
+}
+
+struct SeqDeserializer<'de> {
+    iter: slice::Iter<'de, Value>,
+}
+
+impl<'de> SeqDeserializer<'de> {
+    fn new(items: &'de [Value]) -> Self {
+        SeqDeserializer { iter: items.iter() }
+    }
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = UclError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, UclError> {
+        match self.iter.next() {
+            Some(v) => seed.deserialize(v).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'de> {
+    iter: <&'de Object as IntoIterator>::IntoIter,
+    value: Option<&'de Value>,
+}
+
+impl<'de> MapDeserializer<'de> {
+    fn new(obj: &'de Object) -> Self {
+        MapDeserializer { iter: obj.iter(), value: None }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer<'de> {
+    type Error = UclError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, UclError> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(BorrowedStrDeserializer::new(k)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, UclError> {
+        match self.value.take() {
+            Some(v) => seed.deserialize(v),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: &'de str,
+    value: Option<&'de Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = UclError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), UclError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<UclError>::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for EnumDeserializer<'de> {
+    type Error = UclError;
+
+    fn unit_variant(self) -> Result<(), UclError> {
+        match self.value {
+            None | Some(&Value::Null) => Ok(()),
+            Some(v) => Err(de::Error::invalid_type(unexpected(v), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, UclError> {
+        match self.value {
+            Some(v) => seed.deserialize(v),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, UclError> {
+        match self.value {
+            Some(v) => de::Deserializer::deserialize_seq(v, visitor),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V)
+                                       -> Result<V::Value, UclError> {
+        match self.value {
+            Some(v) => de::Deserializer::deserialize_map(v, visitor),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"struct variant")),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, UclError> for &'de Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::ParseError;
+
+/// A parse failure located in the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The file being parsed, if the source came from a file.
+    pub file: Option<PathBuf>,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+    /// Byte offset into the source.
+    pub offset: usize,
+    /// Tokens the parser would have accepted at `offset`, sorted.
+    pub expected: Vec<String>,
+    /// Human-readable description of the failure.
+    pub message: String,
+    /// The full source line containing `offset`, without its line ending.
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic pointing at `offset` in `source`.
+    pub fn new<T: Into<String>>(source: &str, offset: usize, message: T) -> Diagnostic {
+        let offset = offset.min(source.len());
+        let start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let end = source[offset..].find('\n').map(|i| offset + i).unwrap_or(source.len());
+        Diagnostic {
+            file: None,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[start..offset].chars().count() + 1,
+            offset,
+            expected: Vec::new(),
+            message: message.into(),
+            source_line: source[start..end].trim_end_matches('\r').to_owned(),
+        }
+    }
+
+    /// Converts a raw grammar error into a diagnostic for `source`.
+    pub fn from_parse_error(err: ParseError, source: &str) -> Diagnostic {
+        let mut expected: Vec<String> = err.expected.iter()
+            .map(|s| s.escape_default().to_string())
+            .collect();
+        expected.sort();
+
+        let found = match source[err.offset.min(source.len())..].chars().next() {
+            Some(c) => format!("unexpected `{}`", c.escape_default()),
+            None => "unexpected end of input".to_owned(),
+        };
+        let message = match expected.len() {
+            0 => found,
+            1 => format!("{}, expected `{}`", found, expected[0]),
+            _ => format!("{}, expected one of `{}`", found, expected.join("`, `")),
+        };
+
+        let mut diag = Diagnostic::new(source, err.offset, message);
+        diag.expected = expected;
+        diag
+    }
+
+    pub fn with_file<T: AsRef<Path>>(mut self, file: T) -> Diagnostic {
+        self.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Renders the diagnostic with the offending source line and a caret
+    /// under the failing column.
+    ///
+    /// ```text
+    /// error: unexpected `5`, expected one of `,`, `;`
+    ///  --> site.ucl:2:18
+    ///   |
+    /// 2 |     timeout = 1s 5s }
+    ///   |                  ^
+    /// ```
+    pub fn render(&self) -> String {
+        let lineno = self.line.to_string();
+        let pad = " ".repeat(lineno.len());
+        let caret: String = self.source_line.chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!("error: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}^\n",
+                self.message,
+                pad, self.location(),
+                pad,
+                lineno, self.source_line,
+                pad, caret)
+    }
+
+    pub(crate) fn location(&self) -> String {
+        match self.file {
+            Some(ref file) => format!("{}:{}:{}", file.display(), self.line, self.column),
+            None => format!("{}:{}", self.line, self.column),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location(), self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse, UclError};
+
+    #[test]
+    fn test_render() {
+        let source = "site api {\n    timeout = 1s 5s }\n";
+        let diag = match parse(source) {
+            Err(UclError::Parse(diag)) => diag,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(diag.line, 2);
+        assert_eq!(diag.column, 18);
+        assert_eq!(diag.offset, 28);
+        assert_eq!(diag.expected, vec![",", ";"]);
+        assert_eq!(diag.to_string(), "2:18: unexpected `5`, expected one of `,`, `;`");
+        assert_eq!(diag.render(), "error: unexpected `5`, expected one of `,`, `;`
+ --> 2:18
+  |
+2 |     timeout = 1s 5s }
+  |                  ^
+");
+    }
+}
@@ -0,0 +1,511 @@
+//! Editing UCL text without disturbing its formatting.
+//!
+//! ```
+//! use ucl::Document;
+//!
+//! let mut doc = Document::parse("general {\n    fork = 4; # workers\n}\n").unwrap();
+//! doc.set("general.fork", 8).unwrap();
+//! doc.insert("general.user", "nobody").unwrap();
+//! assert_eq!(doc.to_string(), "general {\n    fork = 8; # workers\n    user = nobody;\n}\n");
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::{emit, include, parser, Parser, UclError, Value};
+use super::emit::Emitter;
+use super::json::JsonStyle;
+use super::node::{Kind, Node, Range};
+use super::path::{self, Segment};
+
+/// A parsed UCL document that keeps its source text.
+///
+/// Edits rewrite only the text of the value they touch, so whitespace,
+/// comments, quoting and heredocs elsewhere are kept byte-for-byte. New
+/// values are written the way `emit::Emitter` writes them, indented like
+/// their neighbours. New lines end with `\r\n` if the source has any and
+/// with `\n` otherwise; only the lines of a heredoc always end with `\n`,
+/// which is part of its string. In a document made of a JSON object or
+/// array, new values are written as pretty-printed JSON instead, indented by
+/// two spaces per level whatever the rest of the document uses. When the
+/// parser expands variables, every `$` in a new string is written as `$$`.
+///
+/// Values are addressed by paths such as `site.api.permissions[1].role`; see
+/// `Spans` for the syntax. Values without text of their own cannot be
+/// edited: implicit arrays collected from repeated keys (their elements
+/// can), the outer sections of `a b { ... }` headers and anything brought
+/// in by `.include`.
+///
+/// The document is not a syntax tree of its own: an edit changes the text,
+/// which is then parsed again in full, so an edit leaving it invalid fails
+/// and changes nothing. Parsing again uses the options of the `Parser` that
+/// made the document, see `Parser::document`, and reads every included file
+/// again.
+#[derive(Debug, Clone)]
+pub struct Document {
+    parser: Parser,
+    source: String,
+    file: Option<(PathBuf, PathBuf)>,
+    root: Node,
+    value: Value,
+}
+
+impl Document {
+    /// Parses a UCL document with the default options. Relative `.include`
+    /// paths are resolved against the current directory.
+    pub fn parse<T: AsRef<str> + ?Sized>(s: &T) -> Result<Document, UclError> {
+        Parser::new().document(s)
+    }
+
+    /// Parses a UCL file with the default options. Relative `.include`
+    /// paths are resolved against the directory of the file, also after
+    /// edits.
+    pub fn parse_file<T: AsRef<Path> + ?Sized>(filename: &T) -> Result<Document, UclError> {
+        Parser::new().document_file(filename)
+    }
+
+    pub(crate) fn new(parser: Parser, source: String, file: Option<(PathBuf, PathBuf)>, root: Node) -> Document {
+        Document { parser, source, file, value: root.clone().into_value(), root }
+    }
+
+    /// Returns the current text of the document.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the document as a `Value`.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the value at `path`.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        self.value.lookup(path)
+    }
+
+    /// Replaces the value at `path`, which must exist.
+    pub fn set<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<(), UclError> {
+        let value = self.escape(value.into());
+        let segments = parse_path(path)?;
+        let json = self.is_json();
+        if segments.is_empty() {
+            let text = if json {
+                self.value_text(&value, "")?
+            } else {
+                Emitter::new().document_text(&value, self.newline())?
+            };
+            let end = self.source.len();
+            return self.splice((0, end), &text);
+        }
+
+        let node = self.find(path, &segments)?;
+        let prefix = self.indent(node.entry.unwrap_or(node.span).0).to_owned();
+        let text = self.value_text(&value, &prefix)?;
+        let was_object = matches!(node.kind, Kind::Object(_));
+        let is_object = matches!(value, Value::Object(_));
+        match (node.entry, node.key) {
+            (Some(entry), Some(key)) if was_object != is_object && !json => {
+                if is_object {
+                    // `key = value;` becomes `key { ... }`.
+                    self.splice((key.1, entry.1), &format!(" {}", text))
+                } else if key.0 == entry.0 {
+                    self.splice((key.1, entry.1), &format!(" = {};", text))
+                } else {
+                    Err(error(format!("cannot replace the section `{}` with a value", path)))
+                }
+            },
+            _ => self.splice(node.span, &text),
+        }
+    }
+
+    /// Adds a value at `path`. The last segment of the path is either a key
+    /// that its object does not have yet or an index at most the length of
+    /// its array, in which case later elements move up by one.
+    pub fn insert<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<(), UclError> {
+        let value = self.escape(value.into());
+        let mut segments = parse_path(path)?;
+        let last = match segments.pop() {
+            Some(last) => last,
+            None => return Err(error("the document root already exists".to_owned())),
+        };
+        let parent = self.find(path, &segments)?;
+        let (start, end) = parent.span;
+
+        match (&parent.kind, last) {
+            (Kind::Object(obj), Segment::Key(ref key)) => {
+                if obj.contains_key(key) {
+                    return Err(error(format!("`{}` already exists", path)));
+                }
+                let last_end = obj.values().filter_map(entry_end).max();
+                let is_root = segments.is_empty();
+                let nl = self.newline();
+
+                if self.is_json() {
+                    let prefix = last_end.map_or("", |at| self.indent(at)).to_owned();
+                    let text = self.entry_text(key, &value, &prefix)?;
+                    return match last_end {
+                        Some(at) if self.source[start..at].contains('\n') => {
+                            self.splice((at, at), &format!(",{}{}{}", nl, prefix, text))
+                        },
+                        Some(at) => self.splice((at, at), &format!(", {}", text)),
+                        None => self.splice((end - 1, end - 1), &text),
+                    };
+                }
+
+                let (prefix, at) = match last_end {
+                    Some(last_end) => (self.indent(last_end).to_owned(), last_end),
+                    None if is_root => (String::new(), end),
+                    None => {
+                        let outer = self.indent(parent.entry.unwrap_or(parent.span).0).to_owned();
+                        let text = self.entry_text(key, &value, &format!("{}    ", outer))?;
+                        return self.splice((end - 1, end - 1), &format!("{}{}    {}{}{}", nl, outer, text, nl, outer));
+                    },
+                };
+                let text = self.entry_text(key, &value, &prefix)?;
+
+                // Go below the last entry, past any comment that ends its
+                // line, unless something else follows it on the line.
+                let line_end = self.source[at..].find('\n').map_or(self.source.len(), |i| at + i);
+                let rest = self.source[at..line_end].trim();
+                if rest.is_empty() || rest.starts_with('#') {
+                    if line_end == self.source.len() {
+                        let sep = if at == 0 || self.source.ends_with('\n') { "" } else { nl };
+                        self.splice((line_end, line_end), &format!("{}{}{}{}", sep, prefix, text, nl))
+                    } else {
+                        self.splice((line_end + 1, line_end + 1), &format!("{}{}{}", prefix, text, nl))
+                    }
+                } else {
+                    self.splice((at, at), &format!(" {}", text))
+                }
+            },
+            (Kind::Array(items), Segment::Index(i)) if i <= items.len() => {
+                let prefix = self.indent(parent.entry.unwrap_or(parent.span).0).to_owned();
+                let text = self.value_text(&value, &prefix)?;
+                if items.is_empty() {
+                    self.splice((start + 1, start + 1), &text)
+                } else if i < items.len() {
+                    let at = items[i].span.0;
+                    self.splice((at, at), &format!("{}, ", text))
+                } else {
+                    let at = items[i - 1].span.1;
+                    self.splice((at, at), &format!(", {}", text))
+                }
+            },
+            _ => Err(error(format!("no place for `{}`", path))),
+        }
+    }
+
+    /// Removes the value at `path` along with its key. A line left empty is
+    /// removed as well, together with a `#` comment that ends it.
+    pub fn remove(&mut self, path: &str) -> Result<(), UclError> {
+        let mut segments = parse_path(path)?;
+        let node = self.find(path, &segments)?;
+        if let Some(entry) = node.entry {
+            if self.is_json() {
+                // Remove the member with the comma that separates it from the
+                // next member, or failing that the previous one.
+                segments.pop();
+                let mut parent = path::find(&self.root, &segments).unwrap();
+                if parent.implicit {
+                    segments.pop();
+                    parent = path::find(&self.root, &segments).unwrap();
+                }
+                let mut members = Vec::new();
+                if let Kind::Object(ref obj) = parent.kind {
+                    for v in obj.values() {
+                        match v.kind {
+                            Kind::Array(ref items) if v.implicit => members.extend(items.iter().filter_map(|v| v.entry)),
+                            _ => members.extend(v.entry),
+                        }
+                    }
+                }
+                members.sort();
+                let i = members.iter().position(|&m| m == entry).unwrap();
+                let span = if i + 1 < members.len() {
+                    (entry.0, members[i + 1].0)
+                } else if i > 0 {
+                    (members[i - 1].1, entry.1)
+                } else {
+                    (parent.span.0 + 1, parent.span.1 - 1)
+                };
+                return self.splice(span, "");
+            }
+
+            let line_start = self.source[..entry.0].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = self.source[entry.1..].find('\n').map_or(self.source.len(), |i| entry.1 + i + 1);
+            let rest = self.source[entry.1..line_end].trim();
+            if self.source[line_start..entry.0].trim().is_empty() && (rest.is_empty() || rest.starts_with('#')) {
+                return self.splice((line_start, line_end), "");
+            }
+            let after = &self.source[entry.1..];
+            let end = entry.1 + after.len() - after.trim_start_matches(&[' ', '\t'][..]).len();
+            return self.splice((entry.0, end), "");
+        }
+
+        // An array element: remove it with the comma that separates it from
+        // the next element, or failing that the previous one.
+        let i = match segments.pop() {
+            Some(Segment::Index(i)) => i,
+            _ => return Err(error("the document root cannot be removed".to_owned())),
+        };
+        let items = match self.find(path, &segments)?.kind {
+            Kind::Array(ref items) => items,
+            _ => unreachable!(),
+        };
+        let span = if i + 1 < items.len() {
+            (items[i].span.0, items[i + 1].span.0)
+        } else if i > 0 {
+            (items[i - 1].span.1, items[i].span.1)
+        } else {
+            items[i].span
+        };
+        self.splice(span, "")
+    }
+
+    fn find(&self, path: &str, segments: &[Segment]) -> Result<&Node, UclError> {
+        match path::find(&self.root, segments) {
+            Some(node) if node.implicit => Err(error(format!("`{}` has no text of its own to edit", path))),
+            Some(node) => Ok(node),
+            None => Err(error(format!("no value at `{}`", path))),
+        }
+    }
+
+    // A document whose root is a JSON object or array is edited as JSON.
+    fn is_json(&self) -> bool {
+        self.source[self.root.span.0..].starts_with(&['{', '['][..])
+    }
+
+    // The line ending of the source, for the lines that edits add.
+    fn newline(&self) -> &'static str {
+        if self.source.contains("\r\n") { "\r\n" } else { "\n" }
+    }
+
+    // With variable expansion on, every `$` is written as `$$`, so that
+    // strings read back as given.
+    fn escape(&self, value: Value) -> Value {
+        if self.parser.expands_variables() { escape_variables(value) } else { value }
+    }
+
+    /// Renders `value` as it would appear after the key of an entry on a
+    /// line indented by `prefix`.
+    fn value_text(&self, value: &Value, prefix: &str) -> Result<String, UclError> {
+        if !self.is_json() {
+            return emit::value_text(value, prefix, self.newline());
+        }
+        let text = value.to_json_string(JsonStyle::Pretty)?;
+        Ok(text.replace('\n', &format!("{}{}", self.newline(), prefix)))
+    }
+
+    /// Renders an entry on a line indented by `prefix`, leaving out the
+    /// indentation of the first line and the line break after it.
+    fn entry_text(&self, key: &str, value: &Value, prefix: &str) -> Result<String, UclError> {
+        if !self.is_json() {
+            return emit::entry_text(key, value, prefix, self.newline());
+        }
+        let key = Value::from(key).to_json_string(JsonStyle::Compact)?;
+        Ok(format!("{}: {}", key, self.value_text(value, prefix)?))
+    }
+
+    /// Returns the indentation of the line containing `offset`.
+    fn indent(&self, offset: usize) -> &str {
+        let start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.source[start..];
+        &line[..line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()]
+    }
+
+    /// Replaces `range` of the source with `text` and parses the result,
+    /// leaving the document untouched if that fails.
+    fn splice(&mut self, range: Range, text: &str) -> Result<(), UclError> {
+        let mut source = String::with_capacity(self.source.len() + text.len());
+        source.push_str(&self.source[..range.0]);
+        source.push_str(text);
+        source.push_str(&self.source[range.1..]);
+
+        let root = self.parser.with(|| match self.file {
+            Some((ref filename, ref path)) => include::parse_as(&source, filename, path.clone()),
+            None => parser::parse_str(&source, None),
+        })?;
+        self.value = root.clone().into_value();
+        self.root = root;
+        self.source = source;
+        Ok(())
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, UclError> {
+    path::parse(path).ok_or_else(|| error(format!("invalid path `{}`", path)))
+}
+
+fn error(msg: String) -> UclError {
+    UclError::Message(msg)
+}
+
+fn escape_variables(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.replace('$', "$$")),
+        Value::Array(items) => Value::Array(items.into_iter().map(escape_variables).collect()),
+        Value::Object(obj) => Value::Object(obj.into_iter().map(|(k, v)| (k, escape_variables(v))).collect()),
+        v => v,
+    }
+}
+
+// Where the text of an object member ends, looking through implicit arrays.
+fn entry_end(node: &Node) -> Option<usize> {
+    let own = node.entry.map(|e| e.1);
+    match node.kind {
+        Kind::Array(ref items) if node.implicit => items.iter().filter_map(entry_end).chain(own).max(),
+        _ => own,
+    }
+}
+
+/// Writes the source text of the document.
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"# Site settings.
+general {
+    user = nobody;   # who we run as
+    fork = 4;
+    /* kept */
+}
+
+site "www" {
+    index = index.html;
+    index = index.htm;
+    ports = [80, 443];
+    motd = <<EOD
+hello
+EOD
+;
+}
+"#;
+
+    #[test]
+    fn test_set() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+        doc.set("general.fork", 8).unwrap();
+        assert_eq!(doc.as_str(), SOURCE.replace("fork = 4;", "fork = 8;"));
+        assert_eq!(doc.get("general.fork"), Some(&Value::from(8)));
+
+        doc.set("site.www.index[1]", "default.htm").unwrap();
+        doc.set("site.www.ports[0]", 8080).unwrap();
+        doc.set("site.www.motd", "bye").unwrap();
+        let expected = SOURCE.replace("fork = 4;", "fork = 8;")
+            .replace("index.htm;", "default.htm;")
+            .replace("[80,", "[8080,")
+            .replace("<<EOD\nhello\nEOD\n;", "bye;");
+        assert_eq!(doc.as_str(), expected);
+
+        doc.set("general.user", Value::from(vec![(super::super::Key::from("name"), Value::from("nobody"))])).unwrap();
+        assert!(doc.as_str().contains("    user {\n        name = nobody;\n    }   # who we run as\n"));
+        doc.set("general.user", 1).unwrap();
+        assert!(doc.as_str().contains("    user = 1;   # who we run as\n"));
+
+        assert!(doc.set("site.www.index", 1).is_err());
+        assert!(doc.set("site", 1).is_err());
+        assert!(doc.set("general.missing", 1).is_err());
+        assert!(doc.set("general..fork", 1).is_err());
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+        doc.insert("general.group", "nogroup").unwrap();
+        doc.insert("site.www.ports[1]", 8443).unwrap();
+        doc.insert("site.www.ports[3]", 9443).unwrap();
+        doc.insert("empty", Value::Object(Default::default())).unwrap();
+        doc.insert("empty.flag", true).unwrap();
+        let expected = SOURCE.replace("    fork = 4;\n", "    fork = 4;\n    group = nogroup;\n")
+            .replace("[80, 443]", "[80, 8443, 443, 9443]")
+            + "empty {\n    flag = true;\n}\n";
+        assert_eq!(doc.as_str(), expected);
+
+        assert!(doc.insert("general.user", "root").is_err());
+        assert!(doc.insert("site.www.ports[9]", 1).is_err());
+        assert!(doc.insert("", 1).is_err());
+
+        let mut doc = Document::parse("a = 1; b = 2;").unwrap();
+        doc.insert("c", 3).unwrap();
+        assert_eq!(doc.as_str(), "a = 1; b = 2;\nc = 3;\n");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut doc = Document::parse(SOURCE).unwrap();
+        doc.remove("general.user").unwrap();
+        doc.remove("site.www.index[0]").unwrap();
+        doc.remove("site.www.ports[1]").unwrap();
+        let expected = SOURCE.replace("    user = nobody;   # who we run as\n", "")
+            .replace("    index = index.html;\n", "")
+            .replace("[80, 443]", "[80]");
+        assert_eq!(doc.as_str(), expected);
+        assert_eq!(doc.get("site.www.index"), Some(&Value::from("index.htm")));
+
+        doc.remove("site.www.ports[0]").unwrap();
+        assert!(doc.as_str().contains("ports = [];"));
+        assert!(doc.remove("site.www.ports[0]").is_err());
+        assert!(doc.remove("").is_err());
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let parser = Parser::new().variable("PORT", "80").max_array_length(2);
+        let mut doc = parser.document("port = $PORT;\nports = [1];\n").unwrap();
+        assert_eq!(doc.get("port"), Some(&Value::from("80")));
+
+        doc.insert("host", "$PORT").unwrap();
+        assert_eq!(doc.get("host"), Some(&Value::from("$PORT")));
+        doc.set("port", "${PORT}0").unwrap();
+        assert_eq!(doc.get("port"), Some(&Value::from("${PORT}0")));
+        doc.insert("ports[1]", 2).unwrap();
+        assert!(doc.insert("ports[2]", 3).is_err());
+        assert_eq!(doc.as_str(), "port = \"$${PORT}0\";\nports = [1, 2];\nhost = \"$$PORT\";\n");
+
+        let mut doc = Document::parse("port = $PORT;\n").unwrap();
+        doc.insert("ports", Value::from(vec![Value::from(1), Value::from(2), Value::from(3)])).unwrap();
+        assert_eq!(doc.get("port"), Some(&Value::from("$PORT")));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut doc = Document::parse("{\n  \"a\": 1,\n  \"b\": [1, 2]\n}\n").unwrap();
+        doc.set("a", "x y").unwrap();
+        doc.insert("b[2]", 3).unwrap();
+        doc.insert("c", Value::from(vec![(super::super::Key::from("d"), Value::from(true))])).unwrap();
+        assert_eq!(doc.as_str(), "{\n  \"a\": \"x y\",\n  \"b\": [1, 2, 3],\n  \"c\": {\n    \"d\": true\n  }\n}\n");
+
+        doc.remove("a").unwrap();
+        doc.remove("c").unwrap();
+        assert_eq!(doc.as_str(), "{\n  \"b\": [1, 2, 3]\n}\n");
+        doc.remove("b").unwrap();
+        assert_eq!(doc.as_str(), "{}\n");
+        doc.insert("e", "f").unwrap();
+        doc.insert("g", 1).unwrap();
+        assert_eq!(doc.as_str(), "{\"e\": \"f\", \"g\": 1}\n");
+
+        let mut doc = Document::parse("[1]").unwrap();
+        doc.set("", Value::from(vec![Value::from("a")])).unwrap();
+        assert_eq!(doc.as_str(), "[\n  \"a\"\n]");
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let source = "general {\r\n    user = nobody;   # who we run as\r\n    fork = 4;\r\n}\r\n";
+        let mut doc = Document::parse(source).unwrap();
+        doc.insert("general.group", "nogroup").unwrap();
+        doc.insert("empty", Value::Object(Default::default())).unwrap();
+        doc.insert("empty.text", "a\nb").unwrap();
+        doc.remove("general.user").unwrap();
+        let expected = "general {\r\n    fork = 4;\r\n    group = nogroup;\r\n}\r\n\
+                        empty {\r\n    text = <<EOD\na\nb\nEOD\n;\r\n}\r\n";
+        assert_eq!(doc.as_str(), expected);
+        assert_eq!(doc.get("empty.text"), Some(&Value::from("a\nb")));
+    }
+}
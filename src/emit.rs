@@ -0,0 +1,522 @@
+//! Writing `Value`s back out as UCL text.
+//!
+//! ```
+//! use ucl::emit::{Emitter, Separator};
+//!
+//! let v = ucl::parse("section { flag = true; }").unwrap();
+//! let text = Emitter::new().indent(2).separator(Separator::Colon).to_string(&v).unwrap();
+//! assert_eq!(text, "section {\n  flag: true;\n}\n");
+//! ```
+
+use std::fmt;
+use std::io;
+
+use super::{Object, UclError, Value};
+
+/// The order in which object keys are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// The order keys were inserted, which for parsed documents is the
+    /// order they appear in the source.
+    Original,
+    /// Lexicographic order.
+    Sorted,
+}
+
+/// The separator written between a key and a simple value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// `key = value;`
+    Equals,
+    /// `key: value;`
+    Colon,
+}
+
+/// Renders `Value`s as UCL documents.
+///
+/// Objects are written with nested section syntax (`section { ... }`) and
+/// simple values are terminated by `;`. Strings are left unquoted when the
+/// parser would read them back unchanged, quoted otherwise, and multi-line
+/// strings become heredocs.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    indent: usize,
+    key_order: KeyOrder,
+    separator: Separator,
+}
+
+impl Default for Emitter {
+    fn default() -> Emitter {
+        Emitter {
+            indent: 4,
+            key_order: KeyOrder::Original,
+            separator: Separator::Equals,
+        }
+    }
+}
+
+impl Emitter {
+    pub fn new() -> Emitter {
+        Emitter::default()
+    }
+
+    /// Sets the number of spaces per nesting level. Defaults to 4.
+    pub fn indent(mut self, width: usize) -> Emitter {
+        self.indent = width;
+        self
+    }
+
+    /// Sets the order of object keys. Defaults to `KeyOrder::Original`.
+    pub fn key_order(mut self, order: KeyOrder) -> Emitter {
+        self.key_order = order;
+        self
+    }
+
+    /// Sets the key/value separator. Defaults to `Separator::Equals`.
+    pub fn separator(mut self, separator: Separator) -> Emitter {
+        self.separator = separator;
+        self
+    }
+
+    /// Renders an object as a UCL document.
+    ///
+    /// Fails if `v` is not an object, or if it contains a non-finite float.
+    pub fn to_string(&self, v: &Value) -> Result<String, UclError> {
+        self.document_text(v, "\n")
+    }
+
+    // Renders an object as a UCL document whose lines end with `newline`.
+    pub(crate) fn document_text(&self, v: &Value, newline: &str) -> Result<String, UclError> {
+        let obj = match *v {
+            Value::Object(ref obj) => obj,
+            _ => return Err(UclError::Message("the root of a UCL document must be an object".to_owned())),
+        };
+        check_finite(v)?;
+        let mut w = Writer::new(self, "", newline);
+        w.write_entries(obj, 0);
+        Ok(w.out)
+    }
+
+    /// Renders an object as a UCL document into `writer`.
+    pub fn to_writer<W: io::Write>(&self, mut writer: W, v: &Value) -> Result<(), UclError> {
+        writer.write_all(self.to_string(v)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Renders an object as a UCL document with the default settings.
+pub fn to_string(v: &Value) -> Result<String, UclError> {
+    Emitter::new().to_string(v)
+}
+
+/// Formats objects as UCL documents and any other value as it would appear
+/// on the right-hand side of `key = ...;`. Non-finite floats are written as
+/// `inf`, `-inf` and `nan`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let emitter = Emitter::new();
+        let mut w = Writer::new(&emitter, "", "\n");
+        match *self {
+            Value::Object(ref obj) => w.write_entries(obj, 0),
+            _ => w.write_value(self, 0),
+        }
+        f.write_str(&w.out)
+    }
+}
+
+fn check_finite(v: &Value) -> Result<(), UclError> {
+    match *v {
+        Value::Float(n) | Value::Time(n) if !n.is_finite() => Err(UclError::NonFiniteFloat(n)),
+        Value::Array(ref items) => items.iter().try_for_each(check_finite),
+        Value::Object(ref obj) => obj.values().try_for_each(check_finite),
+        _ => Ok(()),
+    }
+}
+
+/// Renders `v` as it would appear after `key = ` in a line indented by
+/// `prefix`, ending any further lines with `newline`.
+pub(crate) fn value_text(v: &Value, prefix: &str, newline: &str) -> Result<String, UclError> {
+    check_finite(v)?;
+    let emitter = Emitter::new();
+    let mut w = Writer::new(&emitter, prefix, newline);
+    w.write_value(v, 0);
+    Ok(w.out)
+}
+
+/// Renders a single entry for a line indented by `prefix`, leaving out the
+/// indentation of the first line and the final line break.
+pub(crate) fn entry_text(key: &str, v: &Value, prefix: &str, newline: &str) -> Result<String, UclError> {
+    check_finite(v)?;
+    let emitter = Emitter::new();
+    let mut w = Writer::new(&emitter, prefix, newline);
+    w.write_entries(&Some((key.to_owned(), v.clone())).into_iter().collect(), 0);
+    w.out.truncate(w.out.len() - newline.len());
+    Ok(w.out.split_off(prefix.len()))
+}
+
+struct Writer<'a> {
+    emitter: &'a Emitter,
+    // Written at the start of every line but heredoc bodies.
+    prefix: &'a str,
+    // Ends every line but those of heredocs, whose line breaks are part of
+    // the string.
+    newline: &'a str,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn new(emitter: &'a Emitter, prefix: &'a str, newline: &'a str) -> Writer<'a> {
+        Writer { emitter, prefix, newline, out: String::new() }
+    }
+
+    fn write_entries(&mut self, obj: &Object, depth: usize) {
+        let mut entries: Vec<_> = obj.iter().collect();
+        if self.emitter.key_order == KeyOrder::Sorted {
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+        }
+
+        for (k, v) in entries {
+            self.push_indent(depth);
+            write_key(&mut self.out, k);
+            match *v {
+                Value::Object(ref obj) => {
+                    self.out.push(' ');
+                    self.write_object(obj, depth);
+                },
+                _ => {
+                    self.out.push_str(match self.emitter.separator {
+                        Separator::Equals => " = ",
+                        Separator::Colon => ": ",
+                    });
+                    self.write_value(v, depth);
+                    // Ending every simple value with `;` keeps it from running
+                    // into the next line, whatever the value is.
+                    self.out.push(';');
+                },
+            }
+            self.out.push_str(self.newline);
+        }
+    }
+
+    fn write_object(&mut self, obj: &Object, depth: usize) {
+        if obj.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.out.push_str(self.newline);
+        self.write_entries(obj, depth + 1);
+        self.push_indent(depth);
+        self.out.push('}');
+    }
+
+    fn write_value(&mut self, v: &Value, depth: usize) {
+        match *v {
+            Value::Number(n) => self.out.push_str(&n.to_string()),
+            Value::Float(n) => write_float(&mut self.out, n),
+            Value::Time(n) => write_time(&mut self.out, n),
+            Value::Size(n) => write_size(&mut self.out, n),
+            Value::String(ref s) => write_string(&mut self.out, s),
+            Value::Boolean(b) => self.out.push_str(if b { "true" } else { "false" }),
+            Value::Array(ref items) => self.write_array(items, depth),
+            Value::Object(ref obj) => self.write_object(obj, depth),
+            Value::Null => self.out.push_str("null"),
+        }
+    }
+
+    fn write_array(&mut self, items: &[Value], depth: usize) {
+        let inline = items.iter().all(|v| match *v {
+            Value::Array(_) | Value::Object(_) => false,
+            Value::String(ref s) => !is_multiline(s),
+            _ => true,
+        });
+        if inline {
+            self.out.push('[');
+            for (i, v) in items.iter().enumerate() {
+                if i > 0 {
+                    self.out.push_str(", ");
+                }
+                self.write_value(v, depth);
+            }
+            self.out.push(']');
+            return;
+        }
+
+        self.out.push('[');
+        self.out.push_str(self.newline);
+        for (i, v) in items.iter().enumerate() {
+            self.push_indent(depth + 1);
+            self.write_value(v, depth + 1);
+            if i + 1 < items.len() {
+                if self.out.ends_with('\n') {
+                    self.push_indent(depth + 1);
+                }
+                self.out.push(',');
+            }
+            self.out.push_str(self.newline);
+        }
+        self.push_indent(depth);
+        self.out.push(']');
+    }
+
+    fn push_indent(&mut self, depth: usize) {
+        self.out.push_str(self.prefix);
+        for _ in 0..depth * self.emitter.indent {
+            self.out.push(' ');
+        }
+    }
+}
+
+const TIME_UNITS: [(&str, f64); 7] = [
+    ("d", 86_400.0), ("h", 3_600.0), ("min", 60.0), ("s", 1.0), ("ms", 1e-3), ("us", 1e-6), ("ns", 1e-9),
+];
+
+const SIZE_UNITS: [(&str, i64); 5] = [("pb", 1 << 50), ("tb", 1 << 40), ("gb", 1 << 30), ("mb", 1 << 20), ("kb", 1 << 10)];
+
+// The parser multiplies the number by the unit, so a whole count of units
+// reads back as exactly the same value.
+fn write_time(out: &mut String, n: f64) {
+    if !n.is_finite() {
+        write_float(out, n);
+        return;
+    }
+    if n == 0.0 {
+        out.push_str("0s");
+        return;
+    }
+    for &(unit, secs) in &TIME_UNITS {
+        let count = (n / secs).round();
+        if count != 0.0 && count.abs() < 1e15 && count * secs == n {
+            out.push_str(&format!("{}{}", count, unit));
+            return;
+        }
+    }
+    write_float(out, n);
+    out.push('s');
+}
+
+// A size always takes a suffix, or it would read back as a plain number.
+// Any other size is a fraction of `kb`, which has at most ten decimals.
+fn write_size(out: &mut String, n: i64) {
+    if let Some(&(unit, bytes)) = SIZE_UNITS.iter().find(|&&(_, bytes)| n != 0 && n % bytes == 0) {
+        out.push_str(&format!("{}{}", n / bytes, unit));
+        return;
+    }
+    if n < 0 {
+        out.push('-');
+    }
+    let n = n.unsigned_abs();
+    out.push_str(&(n >> 10).to_string());
+    if n & 1023 != 0 {
+        let frac = format!("{:010}", (n & 1023) * 9_765_625);
+        out.push('.');
+        out.push_str(frac.trim_end_matches('0'));
+    }
+    out.push_str("kb");
+}
+
+fn write_float(out: &mut String, n: f64) {
+    if n.is_nan() {
+        out.push_str("nan");
+    } else if n.is_infinite() {
+        out.push_str(if n > 0.0 { "inf" } else { "-inf" });
+    } else {
+        // `Display` never uses an exponent, but drops the fraction of whole
+        // numbers, which would then read back as integers.
+        let s = n.to_string();
+        out.push_str(&s);
+        if !s.contains('.') {
+            out.push_str(".0");
+        }
+    }
+}
+
+fn write_key(out: &mut String, k: &str) {
+    let raw = k.chars().enumerate().all(|(i, c)| {
+        c.is_ascii_alphanumeric() || c == '_' || (i > 0 && (c == '-' || c == '.'))
+    });
+    if raw && !k.is_empty() {
+        out.push_str(k);
+    } else {
+        write_quoted(out, k);
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    if is_multiline(s) {
+        write_heredoc(out, s);
+    } else if is_raw(s) {
+        out.push_str(s);
+    } else {
+        write_quoted(out, s);
+    }
+}
+
+fn is_multiline(s: &str) -> bool {
+    s.contains(&['\n', '\r', '\u{2028}', '\u{2029}'][..])
+}
+
+// Unquoted strings are limited to a conservative character set, and must not
+// be read back as a number, time, boolean or null. Anything that starts like
+// a number is quoted, in range or not.
+fn is_raw(s: &str) -> bool {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    !s.is_empty()
+        && s.chars().all(|c| c.is_alphanumeric() || "_-./:@+~%".contains(c))
+        && !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && !["true", "yes", "on", "false", "no", "off", "null"].contains(&s)
+        && !["inf", "nan"].contains(&unsigned)
+}
+
+fn write_quoted(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_heredoc(out: &mut String, s: &str) {
+    let mut terminator = "EOD".to_owned();
+    while s.contains(&terminator) {
+        terminator.push('D');
+    }
+    out.push_str("<<");
+    out.push_str(&terminator);
+    out.push('\n');
+    out.push_str(s);
+    out.push('\n');
+    // The terminator must sit alone on its line, so whatever follows the
+    // heredoc, such as the `;` ending the entry, starts on the next one.
+    out.push_str(&terminator);
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{parse, Key};
+
+    fn sample() -> Value {
+        Value::from(vec![
+            (Key::from("name"), Value::from("www")),
+            (Key::from("empty"), Value::Object(Object::new())),
+            (Key::from("nothing"), Value::Null),
+            (Key::from("site"), Value::from(vec![
+                (Key::from("port"), Value::from(8080)),
+                (Key::from("ratio"), Value::from(2.0)),
+                (Key::from("timeout"), Value::Time(0.1)),
+                (Key::from("buffer"), Value::Size(25 << 20)),
+                (Key::from("tags"), Value::from(vec![Value::from("a b"), Value::from("10"), Value::from("")])),
+                (Key::from("banner"), Value::from("line 1\nline 2\n")),
+                (Key::from("hosts"), Value::from(vec![
+                    Value::from(vec![
+                        (Key::from("host"), Value::from("a.example.com")),
+                        (Key::from("roles"), Value::from(vec![
+                            Value::from(vec![(Key::from("role"), Value::from("admin"))]),
+                            Value::Array(vec![]),
+                        ])),
+                    ]),
+                    Value::Object(Object::new()),
+                    Value::from("EOD\nEODD"),
+                ])),
+            ])),
+            (Key::from("odd key"), Value::from(true)),
+        ])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let v = sample();
+        for emitter in &[
+            Emitter::new(),
+            Emitter::new().indent(0).separator(Separator::Colon),
+            Emitter::new().indent(2).key_order(KeyOrder::Sorted),
+        ] {
+            let text = emitter.to_string(&v).unwrap();
+            assert_eq!(parse(&text).unwrap(), v, "{}", text);
+        }
+        assert_eq!(parse(&v.to_string()).unwrap(), v);
+
+        let v = Value::from(vec![
+            (Key::from("raw"), Value::from("$HOME")),
+            (Key::from("quoted"), Value::from("x$$y ${A}")),
+            (Key::from("heredoc"), Value::from("cost: $$5\n")),
+        ]);
+        assert_eq!(parse(&v.to_string()).unwrap(), v);
+
+        let strings = ["99999999999999999999", "-9223372036854775809", "1e400", "0xffffffffffffffffff", "9999999999gb",
+                       "1.5kb", "-.5", "10", "1h30min", "1st", "inf", "-nan", "yes", "off", "null", "x-1", "a.example.com"];
+        let v = Value::from(strings.iter().map(|&s| Value::from(s)).collect::<Vec<_>>());
+        let text = to_string(&Value::from(vec![(Key::from("s"), v.clone())])).unwrap();
+        assert_eq!(parse(&text).unwrap()["s"], v, "{}", text);
+        assert!(text.contains(" x-1, a.example.com]"), "{}", text);
+    }
+
+    #[test]
+    fn test_format() {
+        let v = sample();
+        assert_eq!(Emitter::new().indent(2).key_order(KeyOrder::Sorted).to_string(&v["site"]).unwrap(), r#"banner = <<EOD
+line 1
+line 2
+
+EOD
+;
+buffer = 25mb;
+hosts = [
+  {
+    host = a.example.com;
+    roles = [
+      {
+        role = admin;
+      },
+      []
+    ];
+  },
+  {},
+  <<EODDD
+EOD
+EODD
+EODDD
+
+];
+port = 8080;
+ratio = 2.0;
+tags = ["a b", "10", ""];
+timeout = 100ms;
+"#);
+
+        assert_eq!(parse("b = 1; a { d = 2; c = 3; }").unwrap().to_string(), "b = 1;\na {\n    d = 2;\n    c = 3;\n}\n");
+        assert_eq!(Value::from(1.5).to_string(), "1.5");
+        assert_eq!(Value::from(-3.0).to_string(), "-3.0");
+        assert_eq!(Value::from(f64::NEG_INFINITY).to_string(), "-inf");
+        for &(secs, text) in &[(0.0, "0s"), (5_400.0, "90min"), (-7_200.0, "-2h"), (1.75, "1750ms"), (2e-9, "2ns"), (0.1234567891, "0.1234567891s")] {
+            assert_eq!(Value::Time(secs).to_string(), text);
+            assert_eq!(parse(&format!("t = {};", text)).unwrap()["t"], Value::Time(secs));
+        }
+        assert_eq!(Value::Size(3 << 30).to_string(), "3gb");
+        assert_eq!(Value::Size(-1024).to_string(), "-1kb");
+        for &(bytes, text) in &[(1536, "1.5kb"), (0, "0kb"), (1, "0.0009765625kb"), (-512, "-0.5kb"),
+                                ((1 << 53) + 1, "8796093022208.0009765625kb"),
+                                (i64::MAX, "9007199254740991.9990234375kb"), (i64::MIN, "-8192pb")] {
+            assert_eq!(Value::Size(bytes).to_string(), text);
+            assert_eq!(parse(&format!("s = {};", text)).unwrap()["s"], Value::Size(bytes));
+        }
+        assert_eq!(Value::Time(f64::NAN).to_string(), "nan");
+        assert_eq!(Value::Time(f64::INFINITY).to_string(), "inf");
+        assert_eq!(Value::Time(f64::NEG_INFINITY).to_string(), "-inf");
+        assert_eq!(Value::from("true").to_string(), "\"true\"");
+        assert_eq!(Value::from(":80").to_string(), ":80");
+
+        let err = to_string(&Value::from(vec![(Key::from("x"), Value::from(f64::NAN))])).unwrap_err();
+        assert_eq!(err.to_string(), "non-finite float `NaN` cannot be emitted");
+        assert!(to_string(&Value::from(1)).is_err());
+    }
+}
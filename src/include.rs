@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use glob::{self, Pattern};
+
+use super::{Diagnostic, UclError};
+use super::node::{Entry, Kind, Node, Range};
+use super::parser::{self, parse_str, Limit};
+
+thread_local! {
+    // Canonical paths of the files currently being parsed, innermost last.
+    static STACK: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn parse_file(filename: &Path) -> Result<Node, UclError> {
+    load(filename).map(|(node, _)| node)
+}
+
+/// Parses a file, returning its source text along with the tree.
+pub fn load(filename: &Path) -> Result<(Node, String), UclError> {
+    let path = filename.canonicalize()?;
+    if STACK.with(|s| s.borrow().contains(&path)) {
+        return Err(UclError::IncludeCycle(path));
+    }
+
+    // Read no more than needed to tell that the file is over the size limit,
+    // which may cut a character short; only a complete file has to be UTF-8.
+    let max = parser::with_options(|o| o.limits.get(Limit::InputSize));
+    let mut bytes = Vec::new();
+    File::open(&path)?.take((max as u64).saturating_add(1)).read_to_end(&mut bytes)?;
+    if bytes.len() > max {
+        let head = String::from_utf8_lossy(&bytes[..max]);
+        let diag = Diagnostic::new(&head, 0, parser::limit_message(Limit::InputSize, max));
+        return Err(UclError::LimitExceeded(Limit::InputSize, diag.with_file(filename)));
+    }
+    let source = String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let node = parse_as(&source, filename, path)?;
+    Ok((node, source))
+}
+
+/// Parses `source` as the contents of `filename`, whose canonical path is
+/// `path`.
+pub fn parse_as(source: &str, filename: &Path, path: PathBuf) -> Result<Node, UclError> {
+    STACK.with(|s| s.borrow_mut().push(path));
+    let result = parse_str(source, Some(filename));
+    STACK.with(|s| s.borrow_mut().pop());
+    result
+}
+
+/// Returns the canonical path of the file being parsed.
+pub fn current_file() -> Option<PathBuf> {
+    STACK.with(|s| s.borrow().last().cloned())
+}
+
+/// Expands an `.include` macro into the key/value pairs of the included
+/// files, ready to be merged by `construct_object`. Everything included is
+/// placed at `span`, the location of the macro. The files are parsed at
+/// `priority`, or at the current priority if none is given.
+pub fn include(target: &str, priority: Option<u32>, span: Range) -> Result<Vec<Entry>, &'static str> {
+    let priority = priority.unwrap_or_else(parser::priority);
+    parser::with_priority(priority, || expand(target, span)).map_err(|err| {
+        parser::fail(err);
+        "include failed"
+    })
+}
+
+fn expand(target: &str, span: Range) -> Result<Vec<Entry>, UclError> {
+    let base = STACK.with(|s| {
+        s.borrow().last().and_then(|p| p.parent()).map(Path::to_path_buf)
+    });
+    let base = match base {
+        Some(dir) => dir,
+        None => PathBuf::from("."),
+    };
+
+    let mut kvs = Vec::new();
+    for path in resolve(&base, target)? {
+        if let Kind::Object(obj) = parse_file(&path)?.kind {
+            kvs.extend(obj.into_iter().map(|(k, mut v)| {
+                v.place_at(span);
+                Entry::new(vec![(k, span)], v, span.0, span.1)
+            }));
+        }
+    }
+    Ok(kvs)
+}
+
+fn resolve(base: &Path, target: &str) -> Result<Vec<PathBuf>, UclError> {
+    if !target.contains(&['*', '?', '['][..]) {
+        return Ok(vec![base.join(target)]);
+    }
+
+    let pattern = if Path::new(target).is_absolute() {
+        target.to_owned()
+    } else {
+        let dir = Pattern::escape(&base.to_string_lossy());
+        format!("{}/{}", dir, target)
+    };
+
+    let mut paths = Vec::new();
+    for entry in glob::glob(&pattern)? {
+        paths.push(entry.map_err(io::Error::from)?);
+    }
+    Ok(paths)
+}
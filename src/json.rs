@@ -0,0 +1,170 @@
+//! Writing `Value`s as JSON (RFC 8259).
+
+use std::io;
+
+use super::{UclError, Value};
+
+/// The layout of emitted JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// No insignificant whitespace.
+    Compact,
+    /// One member or element per line, indented by two spaces.
+    Pretty,
+}
+
+impl Value {
+    /// Renders the value as JSON.
+    ///
+    /// Fails with `UclError::NonFiniteFloat` if the value contains a NaN or
+    /// infinite float, which JSON cannot represent.
+    ///
+    /// ```
+    /// use ucl::json::JsonStyle;
+    ///
+    /// let v = ucl::parse("name = \"a\\tb\"; ports = [80, 443];").unwrap();
+    /// assert_eq!(v["ports"].to_json_string(JsonStyle::Compact).unwrap(), "[80,443]");
+    /// assert_eq!(v["name"].to_json_string(JsonStyle::Compact).unwrap(), r#""a\tb""#);
+    /// ```
+    pub fn to_json_string(&self, style: JsonStyle) -> Result<String, UclError> {
+        let mut out = String::new();
+        write_value(&mut out, self, style, 0)?;
+        Ok(out)
+    }
+
+    /// Writes the value as JSON into `writer`. Nothing is written if the
+    /// value cannot be represented.
+    pub fn to_json_writer<W: io::Write>(&self, mut writer: W, style: JsonStyle) -> Result<(), UclError> {
+        writer.write_all(self.to_json_string(style)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn write_value(out: &mut String, v: &Value, style: JsonStyle, depth: usize) -> Result<(), UclError> {
+    match *v {
+        Value::Number(n) | Value::Size(n) => out.push_str(&n.to_string()),
+        Value::Float(n) | Value::Time(n) => {
+            if !n.is_finite() {
+                return Err(UclError::NonFiniteFloat(n));
+            }
+            // Keep whole floats distinguishable from integers.
+            let s = n.to_string();
+            out.push_str(&s);
+            if !s.contains('.') {
+                out.push_str(".0");
+            }
+        },
+        Value::String(ref s) => write_string(out, s),
+        Value::Boolean(b) => out.push_str(if b { "true" } else { "false" }),
+        Value::Null => out.push_str("null"),
+        Value::Array(ref items) => {
+            out.push('[');
+            for (i, v) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, style, depth + 1);
+                write_value(out, v, style, depth + 1)?;
+            }
+            if !items.is_empty() {
+                newline(out, style, depth);
+            }
+            out.push(']');
+        },
+        Value::Object(ref obj) => {
+            out.push('{');
+            for (i, (k, v)) in obj.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, style, depth + 1);
+                write_string(out, k);
+                out.push_str(match style {
+                    JsonStyle::Compact => ":",
+                    JsonStyle::Pretty => ": ",
+                });
+                write_value(out, v, style, depth + 1)?;
+            }
+            if !obj.is_empty() {
+                newline(out, style, depth);
+            }
+            out.push('}');
+        },
+    }
+    Ok(())
+}
+
+fn newline(out: &mut String, style: JsonStyle, depth: usize) {
+    if style == JsonStyle::Pretty {
+        out.push('\n');
+        for _ in 0..depth {
+            out.push_str("  ");
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < '\u{20}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Key;
+
+    #[test]
+    fn test_to_json_string() {
+        let v = Value::from(vec![
+            (Key::from("list"), Value::from(vec![
+                Value::from(1),
+                Value::from(-2.5),
+                Value::from(3.0),
+                Value::Null,
+                Value::Array(vec![]),
+                Value::from(vec![(Key::from("ok"), Value::from(true))]),
+            ])),
+        ]);
+        assert_eq!(v.to_json_string(JsonStyle::Compact).unwrap(),
+                   r#"{"list":[1,-2.5,3.0,null,[],{"ok":true}]}"#);
+        assert_eq!(v.to_json_string(JsonStyle::Pretty).unwrap(), r#"{
+  "list": [
+    1,
+    -2.5,
+    3.0,
+    null,
+    [],
+    {
+      "ok": true
+    }
+  ]
+}"#);
+
+        let v = Value::from(vec![Value::Time(0.1), Value::Time(60.0), Value::Size(1024)]);
+        assert_eq!(v.to_json_string(JsonStyle::Compact).unwrap(), "[0.1,60.0,1024]");
+
+        let s = Value::from("quote \" slash \\ \u{0}\u{8}\u{c}\n\r\t\u{1f} é \u{2028}");
+        assert_eq!(s.to_json_string(JsonStyle::Compact).unwrap(),
+                   "\"quote \\\" slash \\\\ \\u0000\\b\\f\\n\\r\\t\\u001f é \u{2028}\"");
+
+        let mut out = Vec::new();
+        let err = Value::from(vec![Value::from(f64::INFINITY)])
+            .to_json_writer(&mut out, JsonStyle::Compact)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "non-finite float `inf` cannot be emitted");
+        assert!(out.is_empty());
+    }
+}
@@ -1,15 +1,50 @@
-use std::ops::Index;
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
-use std::convert::{From, AsRef};
-use std::io::{self, Read};
-use std::fs::File;
-use std::path::Path;
+extern crate glob;
+extern crate indexmap;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
+use std::ops::Index;
+use std::convert::{From, AsRef, TryFrom};
+use std::error;
+use std::fmt;
+use std::io;
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod convert;
+mod diagnostic;
+mod document;
+mod include;
+mod node;
+mod number;
+mod parser;
+mod path;
+mod span;
+mod vars;
+pub mod emit;
+pub mod json;
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+mod ser;
+
+pub use convert::{ByteSize, ConvertError, FromUcl, HostPort, IpNet};
+pub use diagnostic::Diagnostic;
+pub use document::Document;
+pub use parser::{DuplicateKey, Limit, Parser, UnknownVariable};
+pub use span::{Span, Spans};
+#[cfg(feature = "serde")]
+pub use de::{from_file, from_str, from_value};
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_value, to_writer};
+
+#[allow(clippy::all, ellipsis_inclusive_range_patterns)]
 mod ucl {
     use std::cell::RefCell;
 
-    thread_local!(static TERMINATOR: RefCell<Option<String>> = RefCell::new(None));
+    thread_local!(static TERMINATOR: RefCell<Option<String>> = const { RefCell::new(None) });
 
     include!(concat!(env!("OUT_DIR"), "/ucl.rs"));
 }
@@ -19,7 +54,17 @@ pub use ucl::ParseError;
 #[derive(Debug)]
 pub enum UclError {
     Io(io::Error),
-    Parse(ParseError)
+    Parse(Diagnostic),
+    Pattern(glob::PatternError),
+    IncludeCycle(PathBuf),
+    Message(String),
+    NonFiniteFloat(f64),
+    /// A key repeated in an object against `DuplicateKey::MergeObjects` or
+    /// `DuplicateKey::Error`.
+    DuplicateKey { key: String, first: Box<Diagnostic>, second: Box<Diagnostic> },
+    /// Input beyond one of the limits of the `Parser`.
+    LimitExceeded(Limit, Diagnostic),
+    Convert(ConvertError),
 }
 
 impl From<io::Error> for UclError {
@@ -28,26 +73,91 @@ impl From<io::Error> for UclError {
     }
 }
 
-impl From<ParseError> for UclError {
-    fn from(err: ParseError) -> UclError {
+impl From<Diagnostic> for UclError {
+    fn from(err: Diagnostic) -> UclError {
         UclError::Parse(err)
     }
 }
 
-pub fn parse<T: AsRef<str> + ?Sized>(s: &T) -> Result<Value, ParseError> {
-    ucl::ucl(s.as_ref())
+impl From<ConvertError> for UclError {
+    fn from(err: ConvertError) -> UclError {
+        UclError::Convert(err)
+    }
+}
+
+impl From<glob::PatternError> for UclError {
+    fn from(err: glob::PatternError) -> UclError {
+        UclError::Pattern(err)
+    }
+}
+
+impl fmt::Display for UclError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UclError::Io(ref err) => err.fmt(f),
+            UclError::Parse(ref err) => err.fmt(f),
+            UclError::Pattern(ref err) => write!(f, "invalid include pattern: {}", err),
+            UclError::IncludeCycle(ref path) => write!(f, "include cycle detected at {}", path.display()),
+            UclError::Message(ref msg) => f.write_str(msg),
+            UclError::NonFiniteFloat(n) => write!(f, "non-finite float `{}` cannot be emitted", n),
+            UclError::DuplicateKey { ref key, ref first, ref second } => {
+                write!(f, "{}: duplicate key `{}`, first defined at {}", second.location(), key, first.location())
+            },
+            UclError::LimitExceeded(_, ref err) => err.fmt(f),
+            UclError::Convert(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for UclError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            UclError::Io(ref err) => Some(err),
+            UclError::Pattern(ref err) => Some(err),
+            UclError::Convert(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a UCL document. Relative `.include` paths are resolved against the
+/// current directory.
+///
+/// Failures are a `UclError` rather than a bare `ParseError`, since reading
+/// an included file can fail as well; syntax errors are `UclError::Parse`.
+pub fn parse<T: AsRef<str> + ?Sized>(s: &T) -> Result<Value, UclError> {
+    Parser::new().parse(s)
 }
 
+/// Parses a UCL file. Relative `.include` paths are resolved against the
+/// directory of the including file.
 pub fn parse_file<T: AsRef<Path> + ?Sized>(filename: &T) -> Result<Value, UclError> {
-    let mut source = String::new();
-    File::open(filename.as_ref())?.read_to_string(&mut source)?;
-    Ok(parse(&source)?)
+    Parser::new().parse_file(filename)
+}
+
+/// Parses a UCL document like `parse`, also returning where each key and
+/// value is in the source.
+pub fn parse_spanned<T: AsRef<str> + ?Sized>(s: &T) -> Result<(Value, Spans), UclError> {
+    Parser::new().parse_spanned(s)
+}
+
+/// Parses a UCL file like `parse_file`, also returning where each key and
+/// value is in the file.
+pub fn parse_file_spanned<T: AsRef<Path> + ?Sized>(filename: &T) -> Result<(Value, Spans), UclError> {
+    Parser::new().parse_file_spanned(filename)
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(i64),
     Float(f64),
+    /// A duration in seconds, written with a unit as in `100ms` or `1h30min`.
+    /// It is emitted with the largest unit that keeps it whole.
+    Time(f64),
+    /// A number of bytes, written with a binary suffix as in `25mb` or
+    /// `4kib`. It is emitted with the largest suffix that divides it, or as
+    /// a fraction of `kb` if none does.
+    Size(i64),
     String(String),
     Boolean(bool),
     Array(Array),
@@ -56,20 +166,72 @@ pub enum Value {
 }
 
 pub type Array = Vec<Value>;
-pub type Object = HashMap<String, Value>;
+/// An object keeps its keys in the order they first appear in the source.
+pub type Object = IndexMap<String, Value>;
 
 impl Value {
+    /// Converts the value, panicking with the reason if it cannot be.
     pub fn unwrap<T: FromUcl>(&self) -> T {
-        T::from_ucl(self).unwrap()
+        T::from_ucl(self).unwrap_or_else(|err| panic!("{}", err))
     }
 
     pub fn unwrap_or<T: FromUcl>(&self, def: T) -> T {
         T::from_ucl(self).unwrap_or(def)
     }
 
+    /// Converts the value at `path`, such as `site.api.port`. A missing
+    /// value converts like `null` but is reported as `nothing`; a malformed
+    /// path finds nothing. Errors carry the full path of the offending value.
+    pub fn try_get<T: FromUcl>(&self, path: &str) -> Result<T, ConvertError> {
+        match self.lookup(path) {
+            Some(v) => T::from_ucl(v).map_err(|err| err.within(path)),
+            None => T::from_ucl(&Value::Null).map_err(|err| ConvertError { found: "nothing", ..err.within(path) }),
+        }
+    }
+
+    /// Returns the value at a dotted path such as
+    /// `section."www.example.com".host[1].port`, or `None` if there is none
+    /// or the path is malformed. Keys other than letters, digits, `_` and
+    /// `-` are double quoted. The empty path is the value itself.
+    pub fn lookup(&self, path: &str) -> Option<&Value> {
+        path::lookup(self, &path::parse(path)?)
+    }
+
+    /// Like `lookup`, but returns a mutable reference.
+    pub fn lookup_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path::lookup_mut(self, &path::parse(path)?)
+    }
+
+    /// Returns the value at a JSON Pointer (RFC 6901) such as
+    /// `/section/subsection/host/1/port`, or `None` if there is none or the
+    /// pointer is malformed.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        path::lookup_pointer(self, &path::parse_pointer(pointer)?)
+    }
+
+    /// Like `pointer`, but returns a mutable reference.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        path::lookup_pointer_mut(self, &path::parse_pointer(pointer)?)
+    }
+
+    /// Returns the name of the type of the value, as used in `ConvertError`.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Number(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Time(_) => "time",
+            Value::Size(_) => "size",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Null => "null",
+        }
+    }
+
     pub fn get<T: AsRef<str>>(&self, key: T) -> Option<&Value> {
-        match self {
-            &Value::Object(ref v) => v.get(key.as_ref()),
+        match *self {
+            Value::Object(ref v) => v.get(key.as_ref()),
             _ => None,
         }
     }
@@ -80,23 +242,6 @@ impl Value {
             None => def,
         }
     }
-
-    fn merge(&mut self, other: Value) {
-        if let Value::Object(other) = other {
-            if let Value::Object(ref mut m) = *self {
-                for (k, v) in other {
-                    match m.entry(k) {
-                        Entry::Occupied(mut o) => o.get_mut().merge(v),
-                        Entry::Vacant(o) => { o.insert(v); },
-                    }
-                }
-            } else {
-                *self = Value::Object(other);
-            }
-        } else {
-            *self = other;
-        }
-    }
 }
 
 // impl Index<String> for Value {
@@ -124,15 +269,15 @@ impl Index<usize> for Value {
     type Output = Value;
 
     fn index(&self, idx: usize) -> &Self::Output {
-        match self {
-            &Value::Array(ref v) => &v[idx],
+        match *self {
+            Value::Array(ref v) => &v[idx],
             _ => panic!()
         }
     }
 }
 
 impl<'a> From<&'a str> for Value {
-    fn from(s: &str) -> Self {
+    fn from(s: &'a str) -> Self {
         Value::String(s.to_owned())
     }
 }
@@ -155,6 +300,18 @@ impl From<f64> for Value {
     }
 }
 
+impl From<Duration> for Value {
+    fn from(d: Duration) -> Self {
+        Value::Time(d.as_secs_f64())
+    }
+}
+
+impl From<ByteSize> for Value {
+    fn from(size: ByteSize) -> Self {
+        Value::Size(i64::try_from(size.0).unwrap_or(i64::MAX))
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Value::Boolean(b)
@@ -178,7 +335,7 @@ impl From<Vec<(String, Value)>> for Value {
         let kvs = kvs.into_iter()
             .map(|(k, v)| (Key::from(k), v))
             .collect();
-        Value::Object(construct_object(kvs))
+        Value::Object(node::object_from_pairs(kvs))
     }
 }
 
@@ -194,7 +351,7 @@ impl From<Vec<(String, Value)>> for Value {
 //                     Value::Array(items)
 //                 },
 //                 Value::Null => v,
-//                 other @ _ => Value::Array(vec![other, v]),
+//                 other => Value::Array(vec![other, v]),
 //             };
 //             m.insert(k, newval);
 //         }
@@ -204,56 +361,8 @@ impl From<Vec<(String, Value)>> for Value {
 
 impl From<Vec<(Key, Value)>> for Value {
     fn from(kvs: Vec<(Key, Value)>) -> Self {
-        Value::Object(construct_object(kvs))
-    }
-}
-
-fn construct_object(kvs: Vec<(Key, Value)>) -> Object {
-    // Phase 1: non-unique items into an array
-    let mut m = HashMap::new();
-    for (k, v) in kvs {
-        if !m.contains_key(&k) {
-            m.insert(k, v);
-        } else {
-            let newval = match m.remove(&k).unwrap() {
-                Value::Array(mut items) => {
-                    items.push(v);
-                    Value::Array(items)
-                },
-                Value::Null => v,
-                other @ _ => Value::Array(vec![other, v]),
-            };
-            m.insert(k, newval);
-        }
-    }
-
-    // Phase 2: multiple keys into multi-dimensional HashMap
-    let mut rv: HashMap<String, Value> = HashMap::new();
-    for (k, v) in m {
-        match k {
-            Key::Single(key) => {
-                match rv.entry(key) {
-                    Entry::Occupied(mut o) => o.get_mut().merge(v),
-                    Entry::Vacant(o) => { o.insert(v); },
-                }
-            },
-            Key::Multiple(keys) => {
-                let mut iter = keys.into_iter().rev();
-                let mut key = iter.next().unwrap();
-                let mut value = v;
-                for k in iter {
-                    value = Value::Object([(key, value)].iter().cloned().collect());
-                    key = k;
-                }
-
-                match rv.entry(key) {
-                    Entry::Occupied(mut o) => o.get_mut().merge(value),
-                    Entry::Vacant(o) => { o.insert(value); },
-                }
-            },
-        }
+        Value::Object(node::object_from_pairs(kvs))
     }
-    rv
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -284,69 +393,11 @@ impl From<Vec<String>> for Key {
     }
 }
 
-pub trait FromUcl where Self: Sized {
-    fn from_ucl(v: &Value) -> Option<Self>;
-}
-
-impl FromUcl for i64 {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::Number(n) => Some(n),
-            _ => None,
-        }
-    }
-}
-
-impl FromUcl for f64 {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::Float(n) => Some(n),
-            _ => None,
-        }
-    }
-}
-
-impl FromUcl for String {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::String(ref s) => Some(s.clone()),
-            _ => None,
-        }
-    }
-}
-
-impl FromUcl for bool {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::Boolean(b) => Some(b),
-            _ => None,
-        }
-    }
-}
-
-impl FromUcl for Array {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::Array(ref x) => Some(x.clone()),
-            _ => None,
-        }
-    }
-}
-
-impl FromUcl for Object {
-    fn from_ucl(v: &Value) -> Option<Self> {
-        match v {
-            &Value::Object(ref x) => Some(x.clone()),
-            _ => None,
-        }
-    }
-}
-
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::needless_range_loop)]
 mod tests {
     use super::ucl;
     use super::*;
-    use std::collections::HashMap;
 
     // #[test]
     // fn test_hash_map() {
@@ -374,6 +425,8 @@ mod tests {
         assert_eq!(ucl::jsonValue("false").unwrap(), Value::from(false));
         assert_eq!(ucl::jsonValue("null").unwrap(), Value::Null);
         assert_eq!(ucl::jsonValue(r#""string""#).unwrap(), Value::from("string"));
+        assert_eq!(ucl::jsonValue("-1.5E+3").unwrap(), Value::from(-1500.0));
+        assert_eq!(ucl::jsonValue("1e-2").unwrap(), Value::from(0.01));
 
         let v = ucl::jsonArray(r#"[1, "two", true]"#).unwrap();
         assert_eq!(v, Value::from(vec![
@@ -396,6 +449,30 @@ mod tests {
         ]));
     }
 
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(ucl::jsonValue(r#""\"\\\/\b\f\n\r\t""#).unwrap(), Value::from("\"\\/\u{8}\u{c}\n\r\t"));
+        assert_eq!(ucl::jsonValue(r#""caf\u00e9 \u00E9\u0041""#).unwrap(), Value::from("café éA"));
+        assert_eq!(ucl::jsonValue(r#""\ud83d\ude00 \uD834\uDD1E""#).unwrap(), Value::from("\u{1f600} \u{1d11e}"));
+        assert_eq!(parse(r#"s = "\u00e9\u00e8";"#).unwrap()["s"], Value::from("éè"));
+
+        // Raw strings and keys keep their own, looser escapes.
+        assert_eq!(parse(r#"path = C:\users\x;"#).unwrap()["path"], Value::from("C:usersx"));
+        assert_eq!(parse(r#"a\u = \b\n;"#).unwrap()["au"], Value::from("b\n"));
+
+        for &(src, column, msg) in &[
+            (r#"s = "x\ud83d";"#, 7, "lone surrogate `\\ud83d`"),
+            (r#"s = "x\ud83dy";"#, 7, "lone surrogate `\\ud83d`"),
+            (r#"s = "\ude00\ud83d";"#, 6, "lone surrogate `\\ude00`"),
+            (r#"s = "\u00g9";"#, 6, "invalid unicode escape, expected four hex digits"),
+        ] {
+            match parse(src) {
+                Err(UclError::Parse(diag)) => assert_eq!((diag.column, diag.message.as_str()), (column, msg), "{}", src),
+                other => panic!("unexpected result for {}: {:?}", src, other),
+            }
+        }
+    }
+
     #[test]
     fn test_ucl_array() {
         assert_eq!(ucl::array("[]").unwrap(), Value::Array(vec![]));
@@ -418,8 +495,8 @@ mod tests {
             Value::from(".bar")
         ]));
         assert_eq!(ucl::array("[{},{}]").unwrap(), Value::from(vec![
-            Value::Object(HashMap::new()),
-            Value::Object(HashMap::new())]
+            Value::Object(Object::new()),
+            Value::Object(Object::new())]
         ));
         assert_eq!(ucl::keyValue(r#"object_array = [{
           index = 1;
@@ -465,11 +542,18 @@ mod tests {
         assert_eq!(ucl::value("-10").unwrap(), Value::from(-10));
         assert_eq!(ucl::value("0x1f").unwrap(), Value::from(31));
         assert_eq!(ucl::value("0xFE").unwrap(), Value::from(254));
+        assert_eq!(ucl::value("0b1010").unwrap(), Value::from(10));
+        assert_eq!(ucl::value("0o755").unwrap(), Value::from(493));
+        assert_eq!(ucl::value("0b12").unwrap(), Value::from("0b12"));
 
         assert_eq!(ucl::value("1.23").unwrap(), Value::from(1.23));
         assert_eq!(ucl::value("-1.23").unwrap(), Value::from(-1.23));
         assert_eq!(ucl::value(".12").unwrap(), Value::from(0.12));
         assert_eq!(ucl::value("-0.12").unwrap(), Value::from(-0.12));
+        assert_eq!(ucl::value("1e6").unwrap(), Value::from(1e6));
+        assert_eq!(ucl::value("2.5E-3").unwrap(), Value::from(2.5e-3));
+        assert_eq!(ucl::value("-1.5e+2").unwrap(), Value::from(-150.0));
+        assert_eq!(ucl::value("1e").unwrap(), Value::from("1e"));
 
         assert_eq!(ucl::value("1k").unwrap(), Value::from(1_000));
         assert_eq!(ucl::value("1K").unwrap(), Value::from(1_000));
@@ -478,24 +562,39 @@ mod tests {
         assert_eq!(ucl::value("1g").unwrap(), Value::from(1_000_000_000));
         assert_eq!(ucl::value("1G").unwrap(), Value::from(1_000_000_000));
 
-        assert_eq!(ucl::value("1kb").unwrap(), Value::from(1024));
-        assert_eq!(ucl::value("1Kb").unwrap(), Value::from(1024));
-        assert_eq!(ucl::value("1mb").unwrap(), Value::from(1024 * 1024));
-        assert_eq!(ucl::value("1Mb").unwrap(), Value::from(1024 * 1024));
-        assert_eq!(ucl::value("1gb").unwrap(), Value::from(1024 * 1024 * 1024));
-        assert_eq!(ucl::value("1Gb").unwrap(), Value::from(1024 * 1024 * 1024));
-
-        assert_eq!(ucl::value("1ms").unwrap(), Value::from(0.001));
-        assert_eq!(ucl::value("1.2ms").unwrap(), Value::from(0.0012));
-        assert_eq!(ucl::value(".1s").unwrap(), Value::from(0.1));
-        assert_eq!(ucl::value("-10ms").unwrap(), Value::from(-0.01));
-
-        assert_eq!(ucl::value("1min").unwrap(), Value::from(60_f64));
-        assert_eq!(ucl::value("1.2min").unwrap(), Value::from(72_f64));
-
-        assert_eq!(ucl::value("1d").unwrap(), Value::from((24 * 60 * 60) as f64));
-        assert_eq!(ucl::value("1w").unwrap(), Value::from((7 * 24 * 60 * 60) as f64));
-        assert_eq!(ucl::value("1y").unwrap(), Value::from((365 * 24 * 60 * 60) as f64));
+        assert_eq!(ucl::value("1kb").unwrap(), Value::Size(1024));
+        assert_eq!(ucl::value("1Kb").unwrap(), Value::Size(1024));
+        assert_eq!(ucl::value("1mb").unwrap(), Value::Size(1024 * 1024));
+        assert_eq!(ucl::value("1Mb").unwrap(), Value::Size(1024 * 1024));
+        assert_eq!(ucl::value("1gb").unwrap(), Value::Size(1024 * 1024 * 1024));
+        assert_eq!(ucl::value("1Gb").unwrap(), Value::Size(1024 * 1024 * 1024));
+        assert_eq!(ucl::value("1.5gb").unwrap(), Value::Size(1_610_612_736));
+        assert_eq!(ucl::value("2tb").unwrap(), Value::Size(2_i64 << 40));
+        assert_eq!(ucl::value("500kib").unwrap(), Value::Size(512_000));
+        assert_eq!(ucl::value("4pb").unwrap(), Value::Size(4_i64 << 50));
+        assert_eq!(ucl::value("2.5k").unwrap(), Value::from(2_500));
+        assert_eq!(ucl::value("1.5").unwrap(), Value::from(1.5));
+
+        assert_eq!(ucl::value("1ms").unwrap(), Value::Time(0.001));
+        assert_eq!(ucl::value("1.2ms").unwrap(), Value::Time(0.0012));
+        assert_eq!(ucl::value(".1s").unwrap(), Value::Time(0.1));
+        assert_eq!(ucl::value("-10ms").unwrap(), Value::Time(-0.01));
+
+        assert_eq!(ucl::value("1min").unwrap(), Value::Time(60_f64));
+        assert_eq!(ucl::value("1.2min").unwrap(), Value::Time(72_f64));
+        assert_eq!(ucl::value("2h").unwrap(), Value::Time(7_200_f64));
+        assert_eq!(ucl::value("1h30min").unwrap(), Value::Time(5_400_f64));
+        assert_eq!(ucl::value("2d12h").unwrap(), Value::Time(216_000_f64));
+        assert_eq!(ucl::value("-1h30min").unwrap(), Value::Time(-5_400_f64));
+        assert_eq!(ucl::value("1.5s250ms").unwrap(), Value::Time(1.75));
+        assert_eq!(ucl::value("250us").unwrap(), Value::Time(250e-6));
+        assert_eq!(ucl::value("5ns").unwrap(), Value::Time(5e-9));
+        assert_eq!(ucl::value("1h-30min").unwrap(), Value::from("1h-30min"));
+        assert_eq!(ucl::value("1h30").unwrap(), Value::from("1h30"));
+
+        assert_eq!(ucl::value("1d").unwrap(), Value::Time((24 * 60 * 60) as f64));
+        assert_eq!(ucl::value("1w").unwrap(), Value::Time((7 * 24 * 60 * 60) as f64));
+        assert_eq!(ucl::value("1y").unwrap(), Value::Time((365 * 24 * 60 * 60) as f64));
 
         assert_eq!(ucl::value("true").unwrap(), Value::from(true));
         assert_eq!(ucl::value("false").unwrap(), Value::from(false));
@@ -522,7 +621,7 @@ mod tests {
 
         ucl::keyValue("foo bar = { foo = bar; }").unwrap();
 
-        assert_eq!(ucl::object("{}").unwrap(), Value::from(HashMap::new()));
+        assert_eq!(ucl::object("{}").unwrap(), Value::from(Object::new()));
         assert_eq!(ucl::object(r#"{
           param1=value1;
           param2=value2;
@@ -595,7 +694,7 @@ EOD
                 (Key::from("param1"), Value::from("value1")),
                 (Key::from("flag"), Value::from(true)),
                 (Key::from("number"), Value::from(10_000)),
-                (Key::from("time"), Value::from(0.2)),
+                (Key::from("time"), Value::Time(0.2)),
                 (Key::from("string"), Value::from("something")),
                 (Key::from("subsection"), Value::from(vec![
                     (Key::from("host"), Value::from(vec![
@@ -623,5 +722,247 @@ EOD
 
         assert_eq!(v.get("non_exist"), None);
         assert_eq!(v.get_or("non_exist", 0), 0);
+
+        assert_eq!(v.try_get::<i64>("section.subsection.host[1].port"), Ok(901));
+        assert_eq!(v.try_get::<String>("section.flag"), Err(ConvertError {
+            expected: "string",
+            found: "boolean",
+            value: None,
+            path: "section.flag".to_owned(),
+        }));
+        let err = v.try_get::<i64>("section.timeout").unwrap_err();
+        assert_eq!(err.to_string(), "`section.timeout`: expected integer, found nothing");
+        let err: UclError = v.try_get::<bool>("section.subsection.host[0]").unwrap_err().into();
+        assert_eq!(err.to_string(), "`section.subsection.host[0]`: expected boolean, found object");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected integer, found string")]
+    fn test_unwrap_panic() {
+        parse("fork = four;").unwrap()["fork"].unwrap::<i64>();
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut v = parse(r#"
+            section {
+              subsection { host { port = 900; } host { port = 901; } }
+              "www.example.com" { root = "/var/www"; }
+              "a/b~c" = 1;
+              "" = empty;
+            }
+        "#).unwrap();
+
+        assert_eq!(v.lookup(""), Some(&v));
+        assert_eq!(v.lookup("section.subsection.host[1].port"), Some(&Value::from(901)));
+        assert_eq!(v.lookup(r#"section."www.example.com".root"#), Some(&Value::from("/var/www")));
+        assert_eq!(v.lookup(r#"section."a/b~c""#), Some(&Value::from(1)));
+        assert_eq!(v.lookup("section.subsection.host[2].port"), None);
+        assert_eq!(v.lookup("section.subsection[0]"), None);
+        assert_eq!(v.lookup("section..subsection"), None);
+
+        assert_eq!(v.pointer(""), Some(&v));
+        assert_eq!(v.pointer("/section/subsection/host/1/port"), Some(&Value::from(901)));
+        assert_eq!(v.pointer("/section/www.example.com/root"), Some(&Value::from("/var/www")));
+        assert_eq!(v.pointer("/section/a~1b~0c"), Some(&Value::from(1)));
+        assert_eq!(v.pointer("/section/"), Some(&Value::from("empty")));
+        assert_eq!(v.pointer("/section/subsection/host/01/port"), None);
+        assert_eq!(v.pointer("/section/subsection/host/-"), None);
+        assert_eq!(v.pointer("section"), None);
+
+        *v.lookup_mut("section.subsection.host[0].port").unwrap() = Value::from(800);
+        assert_eq!(v.pointer("/section/subsection/host/0/port"), Some(&Value::from(800)));
+        *v.pointer_mut("/section/www.example.com/root").unwrap() = Value::from("/srv/www");
+        assert_eq!(v.try_get::<String>(r#"section."www.example.com".root"#), Ok("/srv/www".to_owned()));
+        assert_eq!(v.lookup_mut("section.missing"), None);
+        assert_eq!(v.pointer_mut("/section/subsection/host/5"), None);
+    }
+
+    #[test]
+    fn test_ucl_priority() {
+        assert_eq!(parse(r#"
+            a = 1;
+            .priority 2
+            a = 2;
+            b = 1;
+            .priority 1;
+            b = 2;
+            a = 3;
+            c = 1;
+            c = 2;
+        "#).unwrap(), Value::from(vec![
+            (Key::from("a"), Value::from(2)),
+            (Key::from("b"), Value::from(1)),
+            (Key::from("c"), Value::from(vec![Value::from(1), Value::from(2)])),
+        ]));
+
+        assert_eq!(parse(r#"
+            s x { v = 1; w = 1; }
+            .priority 1
+            s x { v = 2; }
+        "#).unwrap(), parse("s { x { v = 2; w = 1; } }").unwrap());
+
+        assert!(parse(".priority 99999999999").is_err());
+    }
+
+    #[test]
+    fn test_ucl_duplicate_keys() {
+        let source = "a = 1;\nb { x = 1; }\na = 2;\nb { y = 2; }\n";
+        let parse_with = |policy, s: &str| Parser::new().duplicate_keys(policy).parse(s);
+
+        assert_eq!(parse_with(DuplicateKey::ImplicitArray, source).unwrap(), parse(source).unwrap());
+        assert_eq!(parse_with(DuplicateKey::LastWins, source).unwrap(),
+                   parse("a = 2; b { y = 2; }").unwrap());
+        assert_eq!(parse_with(DuplicateKey::FirstWins, source).unwrap(),
+                   parse("a = 1; b { x = 1; }").unwrap());
+        assert_eq!(parse_with(DuplicateKey::MergeObjects, "b { x = 1; } b { y = 2; } b z { v = 3; }").unwrap(),
+                   parse("b { x = 1; y = 2; z { v = 3; } }").unwrap());
+
+        match parse_with(DuplicateKey::Error, source) {
+            Err(err @ UclError::DuplicateKey { .. }) => {
+                assert_eq!(err.to_string(), "3:1: duplicate key `a`, first defined at 1:1");
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_with(DuplicateKey::MergeObjects, source) {
+            Err(UclError::DuplicateKey { key, first, second }) => {
+                assert_eq!(key, "a");
+                assert_eq!((first.line, second.line), (1, 3));
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(parse_with(DuplicateKey::Error, "b { x = 1; } b x = 2;").is_err());
+        assert!(parse_with(DuplicateKey::MergeObjects, "b { x = 1; } b { x = 2; }").is_err());
+
+        let json = r#"{"a": 1, "b": {"x": 1}, "a": 2}"#;
+        assert_eq!(parse(json).unwrap(), parse("a = [1, 2]; b { x = 1; }").unwrap());
+        assert_eq!(parse_with(DuplicateKey::LastWins, json).unwrap(), parse("a = 2; b { x = 1; }").unwrap());
+        match parse_with(DuplicateKey::Error, &format!("[\n{}\n]", json)) {
+            Err(err @ UclError::DuplicateKey { .. }) => {
+                assert_eq!(err.to_string(), "2:25: duplicate key `a`, first defined at 2:2");
+            },
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ucl_non_finite_floats() {
+        let source = "a = inf; b = -inf; c = nan;";
+        assert_eq!(parse(source).unwrap()["a"], Value::from("inf"));
+        assert!(parse(r#"{"a": inf}"#).is_err());
+
+        let parser = Parser::new().non_finite_floats(true);
+        let v = parser.parse(source).unwrap();
+        assert_eq!(v["a"], Value::from(f64::INFINITY));
+        assert_eq!(v["b"], Value::from(f64::NEG_INFINITY));
+        assert!(v["c"].unwrap::<f64>().is_nan());
+        assert_eq!(parser.parse(r#"{"a": -inf}"#).unwrap()["a"], Value::from(f64::NEG_INFINITY));
+        assert_eq!(parser.parse("a = info;").unwrap()["a"], Value::from("info"));
+
+        match parse("a = 1e400;") {
+            Err(UclError::Parse(diag)) => assert_eq!(diag.message, "number `1e400` is out of range"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ucl_limits() {
+        let check = |parser: Parser, s: &str, limit, location: &str| match parser.parse(s) {
+            Err(UclError::LimitExceeded(l, diag)) => {
+                assert_eq!(l, limit, "{}", s);
+                assert_eq!(diag.location(), location, "{}", s);
+            },
+            other => panic!("unexpected result for {}: {:?}", s, other),
+        };
+
+        let deep = format!("a = {}1{};", "[".repeat(100_000), "]".repeat(100_000));
+        check(Parser::new(), &deep, Limit::Depth, "1:133");
+        check(Parser::new().max_depth(2), "a { b { c { } } }", Limit::Depth, "1:11");
+        check(Parser::new().max_depth(2), r#"{"a": [[1]]}"#, Limit::Depth, "1:8");
+        check(Parser::new().max_input_size(8), "a = 1;
+b = 2;
+", Limit::InputSize, "1:1");
+        check(Parser::new().max_string_length(3), "a = abcd;", Limit::StringLength, "1:5");
+        check(Parser::new().max_string_length(3), "abcd = a;", Limit::StringLength, "1:1");
+        check(Parser::new().max_string_length(3), r#"{"a": "abcd"}"#, Limit::StringLength, "1:7");
+        check(Parser::new().max_keys(2), "a = 1; b { c = 1; } a = 2; d = 1;", Limit::Keys, "1:28");
+        check(Parser::new().max_array_length(2), "a = [1, 2, 3];", Limit::ArrayLength, "1:12");
+        check(Parser::new().max_array_length(2), "a = 1; a = 2; a = 3;", Limit::ArrayLength, "1:15");
+
+        let parser = Parser::new().max_depth(2).max_input_size(30).max_string_length(3).max_keys(2).max_array_length(2);
+        assert_eq!(parser.parse("abc { a = [1, 2]; b = abc; }").unwrap(),
+                   parse("abc { a = [1, 2]; b = abc; }").unwrap());
+    }
+
+    #[test]
+    fn test_ucl_value_terminators() {
+        let expected = parse(r#"
+            user = nobody;
+            fork = 4;
+            site { port = 80; name = "www"; }
+            ports = [80, 443];
+            motd = <<EOD
+hi
+EOD
+;
+            last = x;
+        "#).unwrap();
+        assert_eq!(parse(r#"
+            user = nobody
+            fork = 4 # workers
+            site { port = 80, name = "www" }
+            ports = [80, 443] /* both */
+            motd = <<EOD
+hi
+EOD
+            last = x"#).unwrap(), expected);
+
+        assert_eq!(ucl::keyValue("param = value").unwrap(), (Key::from("param"), Value::from("value")));
+        assert_eq!(parse("a = 1, b = 2;").unwrap(), parse("a = 1; b = 2;").unwrap());
+        assert_eq!(parse("section { a = 1 };").unwrap(), parse("section { a = 1; }").unwrap());
+        assert!(parse("a = 1 b = 2").is_err());
+    }
+
+    #[test]
+    fn test_ucl_separators() {
+        let expected = parse(r#"
+            user = nobody;
+            ports = [80, 443];
+            site { port = 80; }
+            site api { port = 81; }
+        "#).unwrap();
+        assert_eq!(parse(r#"
+            user: nobody
+            ports: [80, 443]
+            site: { port: 80 }
+            site api: { port 81 }
+        "#).unwrap(), expected);
+        assert_eq!(parse(r#"
+            user nobody;
+            ports [80, 443];
+            site = { port 80; }
+            site api { port = 81; }
+        "#).unwrap(), expected);
+
+        assert_eq!(ucl::keyValue("url http://example.com:8080/").unwrap(),
+                   (Key::from("url"), Value::from("http://example.com:8080/")));
+        assert!(parse("user nobody nogroup").is_err());
+
+        assert_eq!(parse("a:b;").unwrap(), Value::from(vec![(Key::from("a"), Value::from("b"))]));
+        assert_eq!(parse(r#""a:b" = 1;"#).unwrap(), Value::from(vec![(Key::from("a:b"), Value::from(1))]));
+        assert!(parse("a:b = 1;").is_err());
+
+        // An error in one reading of an entry does not stop another one, such
+        // as the words of a section header read as a value, or a value read
+        // as a key.
+        let parser = Parser::new().expand_variables(true).max_string_length(3);
+        let expected = Value::from(vec![(Key::from("k"), Value::from("$$$"))]);
+        assert_eq!(parser.parse(r#"k = "$$$$$$";"#).unwrap(), expected);
+        assert_eq!(parser.parse(r#"k "$$$$$$";"#).unwrap(), expected);
+        let v = Parser::new().unknown_variables(UnknownVariable::Error)
+            .parse("site $X { port = 1; } big 99999999999999999999 { port = 2; }").unwrap();
+        assert_eq!(v.lookup("site.$X.port"), None);
+        assert_eq!(v.try_get::<i64>(r#"site."$X".port"#), Ok(1));
+        assert_eq!(v.try_get::<i64>(r#"big."99999999999999999999".port"#), Ok(2));
     }
 }
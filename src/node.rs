@@ -0,0 +1,313 @@
+//! The parse tree the grammar builds. It carries the source location of every
+//! key and value and is reduced to a plain `Value` once parsing is done.
+
+use std::mem;
+
+use indexmap::IndexMap;
+use indexmap::map::Entry as MapEntry;
+
+use super::{Key, Object, Value};
+use super::parser::{self, DuplicateKey, Limit};
+
+/// A half-open range of byte offsets into the source.
+pub type Range = (usize, usize);
+
+fn union(a: Range, b: Range) -> Range {
+    (a.0.min(b.0), a.1.max(b.1))
+}
+
+#[derive(Debug, Clone)]
+pub enum Kind {
+    Scalar(Value),
+    Array(Vec<Node>),
+    Object(IndexMap<String, Node>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: Kind,
+    /// The text of the value. Implicit containers cover all of their parts.
+    pub span: Range,
+    /// The key the value was written under.
+    pub key: Option<Range>,
+    /// The whole entry the value was written in, key and terminator included.
+    pub entry: Option<Range>,
+    /// Set for values without text of their own: arrays collected from
+    /// repeated keys, the outer objects of `a b { ... }` headers and
+    /// everything brought in by `.include`.
+    pub implicit: bool,
+    /// The `.priority` in effect where the value was written.
+    pub priority: u32,
+}
+
+/// One `key = value` pair, before the pairs of an object are combined.
+pub struct Entry {
+    pub keys: Vec<(String, Range)>,
+    pub span: Range,
+    pub node: Node,
+}
+
+impl Entry {
+    pub fn new(keys: Vec<(String, Range)>, node: Node, start: usize, end: usize) -> Entry {
+        Entry { keys, span: (start, end), node }
+    }
+}
+
+impl Node {
+    fn new(kind: Kind, span: Range) -> Node {
+        Node { kind, span, key: None, entry: None, implicit: false, priority: parser::priority() }
+    }
+
+    pub fn scalar(v: Value, start: usize, end: usize) -> Node {
+        Node::new(Kind::Scalar(v), (start, end))
+    }
+
+    pub fn array(items: Vec<Node>, start: usize, end: usize) -> Result<Node, &'static str> {
+        if let Some(item) = items.get(parser::with_options(|o| o.limits.get(Limit::ArrayLength))) {
+            parser::check_limit(Limit::ArrayLength, items.len(), item.span.0)?;
+        }
+        Ok(Node::new(Kind::Array(items), (start, end)))
+    }
+
+    pub fn object(entries: Vec<Entry>, start: usize, end: usize) -> Result<Node, &'static str> {
+        let policy = parser::with_options(|o| o.duplicate_keys);
+        let obj = construct_object(entries, policy).map_err(|dup| {
+            parser::fail_duplicate(dup);
+            "duplicate key"
+        })?;
+
+        let (max_keys, max_items) = parser::with_options(|o| (o.limits.get(Limit::Keys), o.limits.get(Limit::ArrayLength)));
+        if let Some((_, v)) = obj.get_index(max_keys) {
+            parser::check_limit(Limit::Keys, obj.len(), v.key.unwrap_or(v.span).0)?;
+        }
+        for v in obj.values() {
+            match v.kind {
+                Kind::Array(ref items) if v.implicit => if let Some(item) = items.get(max_items) {
+                    parser::check_limit(Limit::ArrayLength, items.len(), item.key.unwrap_or(item.span).0)?;
+                },
+                _ => (),
+            }
+        }
+        Ok(Node::new(Kind::Object(obj), (start, end)))
+    }
+
+    /// Builds a tree for a value that has no text of its own, such as the
+    /// contents of an included file, placing every part of it at `span`.
+    pub fn from_value(v: Value, span: Range) -> Node {
+        let kind = match v {
+            Value::Array(items) => Kind::Array(items.into_iter().map(|v| Node::from_value(v, span)).collect()),
+            Value::Object(obj) => Kind::Object(obj.into_iter().map(|(k, v)| {
+                let mut node = Node::from_value(v, span);
+                node.key = Some(span);
+                node.entry = Some(span);
+                (k, node)
+            }).collect()),
+            v => Kind::Scalar(v),
+        };
+        let mut node = Node::new(kind, span);
+        node.implicit = true;
+        node
+    }
+
+    /// Places the value and everything in it at `span`, as values without
+    /// text of their own.
+    pub fn place_at(&mut self, span: Range) {
+        self.span = span;
+        self.key = self.key.map(|_| span);
+        self.entry = self.entry.map(|_| span);
+        self.implicit = true;
+        match self.kind {
+            Kind::Scalar(_) => (),
+            Kind::Array(ref mut items) => items.iter_mut().for_each(|v| v.place_at(span)),
+            Kind::Object(ref mut obj) => obj.values_mut().for_each(|v| v.place_at(span)),
+        }
+    }
+
+    pub fn into_value(self) -> Value {
+        match self.kind {
+            Kind::Scalar(v) => v,
+            Kind::Array(items) => Value::Array(items.into_iter().map(Node::into_value).collect()),
+            Kind::Object(obj) => Value::Object(obj.into_iter().map(|(k, v)| (k, v.into_value())).collect()),
+        }
+    }
+
+    fn push(mut self, item: Node) -> Node {
+        self.span = union(self.span, item.span);
+        if let Kind::Array(ref mut items) = self.kind {
+            items.push(item);
+        }
+        self
+    }
+
+    fn merge(&mut self, key: &str, other: Node, policy: DuplicateKey) -> Result<(), Duplicate> {
+        if let Kind::Object(ref mut m) = self.kind {
+            if let Kind::Object(other) = other.kind {
+                for (k, v) in other {
+                    match m.entry(k) {
+                        MapEntry::Occupied(mut o) => {
+                            let key = o.key().clone();
+                            o.get_mut().merge(&key, v, policy)?;
+                        },
+                        MapEntry::Vacant(o) => { o.insert(v); },
+                    }
+                }
+                return Ok(());
+            }
+        }
+        if other.priority != self.priority {
+            if other.priority > self.priority {
+                *self = other;
+            }
+            return Ok(());
+        }
+        match policy {
+            DuplicateKey::ImplicitArray | DuplicateKey::LastWins => *self = other,
+            DuplicateKey::FirstWins => (),
+            DuplicateKey::MergeObjects | DuplicateKey::Error => return Err(Duplicate::new(key, self, &other)),
+        }
+        Ok(())
+    }
+}
+
+/// A key given twice where the duplicate-key policy does not allow it.
+pub struct Duplicate {
+    pub key: String,
+    /// Offsets of the first and the second key.
+    pub first: usize,
+    pub second: usize,
+}
+
+impl Duplicate {
+    fn new(key: &str, first: &Node, second: &Node) -> Duplicate {
+        Duplicate {
+            key: key.to_owned(),
+            first: first.key.unwrap_or(first.span).0,
+            second: second.key.unwrap_or(second.span).0,
+        }
+    }
+}
+
+/// Resolves a key given twice with different priorities. Objects are merged
+/// key by key; anything else is taken from the higher priority.
+fn prefer(prev: Node, node: Node) -> Node {
+    let (low, high) = if prev.priority < node.priority { (prev, node) } else { (node, prev) };
+    match (low.kind, high.kind) {
+        (Kind::Object(mut m), Kind::Object(other)) => {
+            for (k, v) in other {
+                match m.entry(k) {
+                    MapEntry::Occupied(mut o) => {
+                        let current = o.get_mut();
+                        if current.priority != v.priority {
+                            let prev = mem::replace(current, Node::new(Kind::Scalar(Value::Null), v.span));
+                            *current = prefer(prev, v);
+                        } else {
+                            *current = v;
+                        }
+                    },
+                    MapEntry::Vacant(o) => { o.insert(v); },
+                }
+            }
+            Node { kind: Kind::Object(m), implicit: true, ..high }
+        },
+        (_, kind) => Node { kind, ..high },
+    }
+}
+
+/// Combines the pairs of an object: repeated keys are resolved by `policy`
+/// and `a b { ... }` headers nest into objects that merge with their
+/// siblings. A key repeated with a different priority is resolved by
+/// `prefer` instead.
+pub fn construct_object(entries: Vec<Entry>, policy: DuplicateKey) -> Result<IndexMap<String, Node>, Duplicate> {
+    // Phase 1: non-unique items into an array
+    let mut m: IndexMap<Key, (Vec<Range>, Node)> = IndexMap::new();
+    for Entry { keys, span, mut node } in entries {
+        let (names, ranges): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+        node.key = ranges.last().cloned();
+        node.entry = Some(span);
+        match m.entry(Key::from(names)) {
+            MapEntry::Vacant(o) => { o.insert((ranges, node)); },
+            MapEntry::Occupied(mut o) => {
+                let name = match *o.key() {
+                    Key::Single(ref k) => k.clone(),
+                    Key::Multiple(ref keys) => keys.join(" "),
+                };
+                let current = &mut o.get_mut().1;
+                let prev = mem::replace(current, Node::new(Kind::Scalar(Value::Null), span));
+                if prev.priority != node.priority {
+                    *current = prefer(prev, node);
+                    continue;
+                }
+                *current = match (policy, &prev.kind, &node.kind) {
+                    (DuplicateKey::ImplicitArray, _, _) => match prev.kind {
+                        Kind::Scalar(Value::Null) => node,
+                        Kind::Array(_) => prev.push(node),
+                        _ => Node {
+                            span: union(prev.span, node.span),
+                            key: prev.key,
+                            entry: prev.entry,
+                            implicit: true,
+                            priority: prev.priority,
+                            kind: Kind::Array(vec![prev, node]),
+                        },
+                    },
+                    (DuplicateKey::LastWins, _, _) => node,
+                    (DuplicateKey::FirstWins, _, _) => prev,
+                    (DuplicateKey::MergeObjects, &Kind::Object(_), &Kind::Object(_)) => {
+                        let mut prev = prev;
+                        prev.merge(&name, node, policy)?;
+                        prev.implicit = true;
+                        prev
+                    },
+                    _ => return Err(Duplicate::new(&name, &prev, &node)),
+                };
+            },
+        }
+    }
+
+    // Phase 2: multiple keys into multi-dimensional map
+    let mut rv: IndexMap<String, Node> = IndexMap::new();
+    for (k, (ranges, node)) in m {
+        let (key, node) = match k {
+            Key::Single(key) => (key, node),
+            Key::Multiple(keys) => {
+                let mut iter = keys.into_iter().zip(ranges).rev();
+                let (mut key, _) = iter.next().unwrap();
+                let mut value = node;
+                for (k, range) in iter {
+                    let entry = value.entry;
+                    let mut outer = Node::new(Kind::Object(Some((key, value)).into_iter().collect()),
+                                              entry.unwrap_or(range));
+                    outer.key = Some(range);
+                    outer.entry = entry;
+                    outer.implicit = true;
+                    value = outer;
+                    key = k;
+                }
+                (key, value)
+            },
+        };
+        match rv.entry(key) {
+            MapEntry::Occupied(mut o) => {
+                let key = o.key().clone();
+                o.get_mut().merge(&key, node, policy)?;
+            },
+            MapEntry::Vacant(o) => { o.insert(node); },
+        }
+    }
+    Ok(rv)
+}
+
+/// Combines the pairs of an object built in code rather than parsed.
+pub fn object_from_pairs(kvs: Vec<(Key, Value)>) -> Object {
+    let entries = kvs.into_iter().map(|(k, v)| {
+        let keys = match k {
+            Key::Single(k) => vec![(k, (0, 0))],
+            Key::Multiple(keys) => keys.into_iter().map(|k| (k, (0, 0))).collect(),
+        };
+        Entry::new(keys, Node::from_value(v, (0, 0)), 0, 0)
+    }).collect();
+    match construct_object(entries, DuplicateKey::ImplicitArray) {
+        Ok(obj) => obj.into_iter().map(|(k, v)| (k, v.into_value())).collect(),
+        Err(_) => unreachable!("implicit arrays take any duplicate"),
+    }
+}
@@ -0,0 +1,145 @@
+//! Conversion of numeric literals.
+//!
+//! The grammar decides what looks like a number; the conversions here may
+//! still fail on values that do not fit, and report where they are.
+
+use std::convert::TryFrom;
+
+use super::parser;
+
+/// Reads an integer: decimal, or hexadecimal, binary or octal after `0x`,
+/// `0b` or `0o`. It may be followed by `k`, `m`, `g`, `t` or `p` for powers
+/// of 1000, or by the same with `b` or `ib` for powers of 1024. Before such a
+/// suffix a decimal may have a fraction, and the product is rounded. `text`
+/// is at `offset` of the source.
+pub fn integer(text: &str, offset: usize) -> Result<i64, &'static str> {
+    let (digits, multiplier) = split_multiplier(text);
+    let n = if digits.contains('.') {
+        fraction(digits, multiplier)
+    } else {
+        let n = match digits.get(..2) {
+            Some("0x") => i64::from_str_radix(&digits[2..], 16),
+            Some("0b") => i64::from_str_radix(&digits[2..], 2),
+            Some("0o") => i64::from_str_radix(&digits[2..], 8),
+            _ => digits.parse(),
+        };
+        n.ok().and_then(|n| n.checked_mul(multiplier))
+    };
+    n.ok_or_else(|| out_of_range(text, offset))
+}
+
+/// Reads a decimal float, possibly with an exponent. A magnitude too large
+/// for an `f64` is out of range rather than infinite.
+pub fn float(text: &str, offset: usize) -> Result<f64, &'static str> {
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(out_of_range(text, offset)),
+    }
+}
+
+/// Reads `inf`, `-inf` or `nan`, if the parser allows them.
+pub fn non_finite(text: &str) -> Result<f64, &'static str> {
+    if !parser::with_options(|o| o.non_finite_floats) {
+        return Err("number");
+    }
+    Ok(match text {
+        "inf" => f64::INFINITY,
+        "-inf" => f64::NEG_INFINITY,
+        _ => f64::NAN,
+    })
+}
+
+/// Adds up the parts of a duration at `offset`, each in seconds.
+pub fn duration(parts: &[f64], negative: bool, offset: usize) -> Result<f64, &'static str> {
+    let n: f64 = parts.iter().sum();
+    if !n.is_finite() {
+        parser::fail_at(offset, "duration is out of range");
+        return Err("duration out of range");
+    }
+    Ok(if negative { -n } else { n })
+}
+
+// Multiplies a decimal with a fraction exactly, rounding half away from
+// zero. Digits of the fraction past what an `i128` can carry only matter
+// for ties, and are dropped.
+fn fraction(digits: &str, multiplier: i64) -> Option<i64> {
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits),
+    };
+    let (whole, frac) = digits.split_at(digits.find('.')?);
+    let frac = &frac[1..frac.len().min(23)];
+    let whole: i128 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let scale = 10i128.pow(frac.len() as u32);
+    let frac = if frac.is_empty() { 0 } else { frac.parse::<i128>().ok()? * i128::from(multiplier) };
+    let n = whole.checked_mul(i128::from(multiplier))?.checked_add((frac + scale / 2) / scale)?;
+    i64::try_from(if negative { -n } else { n }).ok()
+}
+
+fn out_of_range(text: &str, offset: usize) -> &'static str {
+    parser::fail_at(offset, format!("number `{}` is out of range", text));
+    "number out of range"
+}
+
+// None of the prefix letters can be a digit, so the first one starts the
+// suffix.
+fn split_multiplier(text: &str) -> (&str, i64) {
+    let i = match text.find(|c: char| "kKmMgGtTpP".contains(c)) {
+        Some(i) => i,
+        None => return (text, 1),
+    };
+    let (digits, suffix) = text.split_at(i);
+    let power = match &suffix[..1] {
+        "k" | "K" => 1,
+        "m" | "M" => 2,
+        "g" | "G" => 3,
+        "t" | "T" => 4,
+        _ => 5,
+    };
+    let base: i64 = if suffix.len() > 1 { 1024 } else { 1000 };
+    (digits, base.pow(power))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(integer("-12", 0), Ok(-12));
+        assert_eq!(integer("0xfb", 0), Ok(0xfb));
+        assert_eq!(integer("0b1010", 0), Ok(10));
+        assert_eq!(integer("0o755", 0), Ok(0o755));
+        assert_eq!(integer("0b1kb", 0), Ok(1_024));
+        assert_eq!(integer("0x1kb", 0), Ok(1_024));
+        assert_eq!(integer("2K", 0), Ok(2_000));
+        assert_eq!(integer("3gb", 0), Ok(3 * 1_073_741_824));
+        assert_eq!(integer("3gib", 0), Ok(3 * 1_073_741_824));
+        assert_eq!(integer("2T", 0), Ok(2_000_000_000_000));
+        assert_eq!(integer("4pb", 0), Ok(4 << 50));
+        assert_eq!(integer("1.5gb", 0), Ok(1_610_612_736));
+        assert_eq!(integer("-.5kib", 0), Ok(-512));
+        assert_eq!(integer("0.0015k", 0), Ok(2));
+        assert_eq!(integer("8191.5pib", 0), Ok(9_222_809_086_901_354_496));
+        assert_eq!(integer("9223372036854775807", 0), Ok(i64::MAX));
+        assert_eq!(integer("-9223372036854775808", 0), Ok(i64::MIN));
+        assert_eq!(integer("1.4995k", 0), Ok(1_500));
+        assert_eq!(integer("-1.0005k", 0), Ok(-1_001));
+        assert_eq!(integer("9007199254740991.9990234375kb", 0), Ok(i64::MAX));
+        assert_eq!(integer("-9007199254740992kb", 0), Ok(i64::MIN));
+
+        for text in &["9223372036854775808", "99999999999999999999", "0x8000000000000000", "9999999999g", "-9999999999gb",
+                     "8192pib", "8192.0pib", "-9000000p"] {
+            assert_eq!(integer(text, 0), Err("number out of range"), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(float("1e6", 0), Ok(1e6));
+        assert_eq!(float("2.5E-3", 0), Ok(0.0025));
+        assert_eq!(float("-.5e+1", 0), Ok(-5.0));
+        assert_eq!(float("1e400", 0), Err("number out of range"));
+        assert_eq!(float("-1e400", 0), Err("number out of range"));
+    }
+}
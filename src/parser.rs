@@ -0,0 +1,483 @@
+//! Parser options.
+//!
+//! ```
+//! use ucl::{Parser, UnknownVariable};
+//!
+//! let v = Parser::new()
+//!     .variable("PREFIX", "/usr/local")
+//!     .unknown_variables(UnknownVariable::Error)
+//!     .parse("root = ${PREFIX}/www; motd = \"cost: $$5\";")
+//!     .unwrap();
+//! assert_eq!(v["root"].unwrap::<String>(), "/usr/local/www");
+//! assert_eq!(v["motd"].unwrap::<String>(), "cost: $5");
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use super::{include, ucl, Diagnostic, Document, Spans, UclError, Value};
+use super::node::{Duplicate, Node};
+
+/// What to do with a reference to a variable that is not defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownVariable {
+    /// Leave the reference in the string as written.
+    Keep,
+    /// Replace the reference with nothing.
+    Empty,
+    /// Fail the parse.
+    Error,
+}
+
+/// What to do with a key that appears more than once in an object.
+///
+/// Only keys with the same priority are subject to the policy; otherwise
+/// the higher `.priority` wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// Collect the values into an array.
+    ImplicitArray,
+    /// Keep the last value.
+    LastWins,
+    /// Keep the first value.
+    FirstWins,
+    /// Merge objects key by key, and fail on anything else.
+    MergeObjects,
+    /// Fail the parse.
+    Error,
+}
+
+/// A limit on the input the parser accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// How deeply arrays and objects nest.
+    Depth,
+    /// The size of the source in bytes. Each included file is counted on
+    /// its own.
+    InputSize,
+    /// The length of a key or a string in bytes.
+    StringLength,
+    /// The number of keys in an object.
+    Keys,
+    /// The number of items in an array, implicit ones included.
+    ArrayLength,
+}
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    depth: usize,
+    input_size: usize,
+    string_length: usize,
+    keys: usize,
+    array_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            depth: 128,
+            input_size: 16 * 1024 * 1024,
+            string_length: 1024 * 1024,
+            keys: 10_000,
+            array_length: 100_000,
+        }
+    }
+}
+
+impl Limits {
+    fn get_mut(&mut self, limit: Limit) -> &mut usize {
+        match limit {
+            Limit::Depth => &mut self.depth,
+            Limit::InputSize => &mut self.input_size,
+            Limit::StringLength => &mut self.string_length,
+            Limit::Keys => &mut self.keys,
+            Limit::ArrayLength => &mut self.array_length,
+        }
+    }
+
+    pub fn get(&self, limit: Limit) -> usize {
+        match limit {
+            Limit::Depth => self.depth,
+            Limit::InputSize => self.input_size,
+            Limit::StringLength => self.string_length,
+            Limit::Keys => self.keys,
+            Limit::ArrayLength => self.array_length,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub variables: IndexMap<String, String>,
+    pub env: Vec<String>,
+    pub unknown_variables: UnknownVariable,
+    pub expand_variables: bool,
+    pub duplicate_keys: DuplicateKey,
+    pub non_finite_floats: bool,
+    pub limits: Limits,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            variables: IndexMap::new(),
+            env: Vec::new(),
+            unknown_variables: UnknownVariable::Keep,
+            expand_variables: false,
+            duplicate_keys: DuplicateKey::ImplicitArray,
+            non_finite_floats: false,
+            limits: Limits::default(),
+        }
+    }
+}
+
+thread_local! {
+    // The options of the parse in progress.
+    static OPTIONS: RefCell<Options> = RefCell::new(Options::default());
+    // The priority given to values as they are parsed.
+    static PRIORITY: Cell<u32> = const { Cell::new(0) };
+    // The number of arrays and objects open at the current position.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    // Errors raised by grammar actions; the grammar can only fail with a
+    // static message, so the real errors are parked here until parsing ends.
+    // They only count if the parse fails, as an action may fail in an
+    // alternative that the grammar backtracks out of.
+    static ERROR: RefCell<Vec<Failure>> = const { RefCell::new(Vec::new()) };
+}
+
+enum Failure {
+    Error(UclError),
+    // Become diagnostics once the source is at hand.
+    At(usize, String),
+    Duplicate(Duplicate),
+    Limit(Limit, usize, String),
+}
+
+impl Failure {
+    fn offset(&self) -> Option<usize> {
+        match *self {
+            Failure::Error(_) => None,
+            Failure::At(offset, _) | Failure::Limit(_, offset, _) => Some(offset),
+            Failure::Duplicate(ref dup) => Some(dup.second),
+        }
+    }
+}
+
+/// Parses UCL with non-default options.
+///
+/// With variable expansion on, strings, quoted or not, may refer to
+/// variables as `$NAME` or `${NAME}`, where a name is made of ASCII letters,
+/// digits and `_`. A reference is looked up in the variables given to the
+/// parser, then in the built-ins and last in the allowed environment
+/// variables. `$$` stands for a single `$`. Expansion leaves the value a
+/// string: `$PORT` never becomes a number. It is off unless turned on with
+/// `expand_variables` or by any of the variable options, so `ucl::parse`
+/// leaves every `$` as written.
+///
+/// When parsing a file the built-ins `$FILENAME` and `$CURDIR` hold the
+/// canonical path of the file being read and of its directory.
+///
+/// The parser refuses input beyond its limits with
+/// `UclError::LimitExceeded`. By default arrays and objects nest at most
+/// 128 deep, a source is at most 16 MiB, a key or string at most 1 MiB, an
+/// object has at most 10,000 keys and an array at most 100,000 items.
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    options: Options,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Turns variable expansion on or off. Defining a variable, allowing an
+    /// environment variable or setting the policy for unknown variables
+    /// turns it on as well.
+    pub fn expand_variables(mut self, expand: bool) -> Parser {
+        self.options.expand_variables = expand;
+        self
+    }
+
+    /// Defines the variable `name`.
+    pub fn variable<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Parser {
+        self.options.variables.insert(name.into(), value.into());
+        self.options.expand_variables = true;
+        self
+    }
+
+    /// Defines a variable for each pair.
+    pub fn variables<I, K, V>(mut self, vars: I) -> Parser
+        where I: IntoIterator<Item = (K, V)>, K: Into<String>, V: Into<String>
+    {
+        self.options.variables.extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self.options.expand_variables = true;
+        self
+    }
+
+    /// Lets references to the environment variable `name` expand to its
+    /// value. No environment variable is visible unless allowed here.
+    pub fn env<T: Into<String>>(mut self, name: T) -> Parser {
+        self.options.env.push(name.into());
+        self.options.expand_variables = true;
+        self
+    }
+
+    /// Sets what happens to references to undefined variables. Defaults to
+    /// `UnknownVariable::Keep`.
+    pub fn unknown_variables(mut self, policy: UnknownVariable) -> Parser {
+        self.options.unknown_variables = policy;
+        self.options.expand_variables = true;
+        self
+    }
+
+    /// Sets what happens to keys repeated in an object, in UCL and JSON
+    /// alike. Defaults to `DuplicateKey::ImplicitArray`.
+    pub fn duplicate_keys(mut self, policy: DuplicateKey) -> Parser {
+        self.options.duplicate_keys = policy;
+        self
+    }
+
+    /// Lets the unquoted values `inf`, `-inf` and `nan` stand for the
+    /// non-finite floats, in UCL and JSON alike. Off by default, as JSON does
+    /// not have them; UCL then reads them as strings.
+    pub fn non_finite_floats(mut self, allow: bool) -> Parser {
+        self.options.non_finite_floats = allow;
+        self
+    }
+
+    /// Sets how deeply arrays and objects may nest.
+    pub fn max_depth(self, n: usize) -> Parser {
+        self.limit(Limit::Depth, n)
+    }
+
+    /// Sets the largest source accepted, in bytes. The limit applies to each
+    /// included file on its own.
+    pub fn max_input_size(self, n: usize) -> Parser {
+        self.limit(Limit::InputSize, n)
+    }
+
+    /// Sets the longest key or string accepted, in bytes.
+    pub fn max_string_length(self, n: usize) -> Parser {
+        self.limit(Limit::StringLength, n)
+    }
+
+    /// Sets how many keys an object may have.
+    pub fn max_keys(self, n: usize) -> Parser {
+        self.limit(Limit::Keys, n)
+    }
+
+    /// Sets how many items an array may have.
+    pub fn max_array_length(self, n: usize) -> Parser {
+        self.limit(Limit::ArrayLength, n)
+    }
+
+    fn limit(mut self, limit: Limit, n: usize) -> Parser {
+        *self.options.limits.get_mut(limit) = n;
+        self
+    }
+
+    /// Parses a UCL document. Relative `.include` paths are resolved against
+    /// the current directory.
+    pub fn parse<T: AsRef<str> + ?Sized>(&self, s: &T) -> Result<Value, UclError> {
+        self.with(|| parse_str(s.as_ref(), None)).map(Node::into_value)
+    }
+
+    /// Parses a UCL file. Relative `.include` paths are resolved against the
+    /// directory of the including file.
+    pub fn parse_file<T: AsRef<Path> + ?Sized>(&self, filename: &T) -> Result<Value, UclError> {
+        self.with(|| include::load(filename.as_ref())).map(|(node, _)| node.into_value())
+    }
+
+    /// Parses a UCL document like `parse`, also returning where each key and
+    /// value is in the source.
+    pub fn parse_spanned<T: AsRef<str> + ?Sized>(&self, s: &T) -> Result<(Value, Spans), UclError> {
+        let node = self.with(|| parse_str(s.as_ref(), None))?;
+        let spans = Spans::new(&node, s.as_ref());
+        Ok((node.into_value(), spans))
+    }
+
+    /// Parses a UCL document into a `Document` for editing. Edits parse it
+    /// again with the same options.
+    pub fn document<T: AsRef<str> + ?Sized>(&self, s: &T) -> Result<Document, UclError> {
+        let root = self.with(|| parse_str(s.as_ref(), None))?;
+        Ok(Document::new(self.clone(), s.as_ref().to_owned(), None, root))
+    }
+
+    /// Parses a UCL file into a `Document` for editing. Relative `.include`
+    /// paths are resolved against the directory of the file, also after
+    /// edits.
+    pub fn document_file<T: AsRef<Path> + ?Sized>(&self, filename: &T) -> Result<Document, UclError> {
+        let filename = filename.as_ref();
+        let (root, source) = self.with(|| include::load(filename))?;
+        let file = (filename.to_path_buf(), filename.canonicalize()?);
+        Ok(Document::new(self.clone(), source, Some(file), root))
+    }
+
+    /// Parses a UCL file like `parse_file`, also returning where each key
+    /// and value is in the file.
+    pub fn parse_file_spanned<T: AsRef<Path> + ?Sized>(&self, filename: &T) -> Result<(Value, Spans), UclError> {
+        let (node, source) = self.with(|| include::load(filename.as_ref()))?;
+        let spans = Spans::new(&node, &source);
+        Ok((node.into_value(), spans))
+    }
+
+    /// Whether this parser expands variables in strings.
+    pub(crate) fn expands_variables(&self) -> bool {
+        self.options.expand_variables
+    }
+
+    /// Runs `f` with the options of this parser.
+    pub(crate) fn with<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let saved = OPTIONS.with(|o| mem::replace(&mut *o.borrow_mut(), self.options.clone()));
+        let depth = DEPTH.with(|d| d.replace(0));
+        let rv = f();
+        DEPTH.with(|d| d.set(depth));
+        OPTIONS.with(|o| *o.borrow_mut() = saved);
+        rv
+    }
+}
+
+/// Runs `f` with the options of the parse in progress.
+pub fn with_options<T, F: FnOnce(&Options) -> T>(f: F) -> T {
+    OPTIONS.with(|o| f(&o.borrow()))
+}
+
+/// Parses `source` with the current options. A `.priority` in the source
+/// lasts until the end of it.
+pub fn parse_str(source: &str, file: Option<&Path>) -> Result<Node, UclError> {
+    let locate = |offset, msg| {
+        let diag = Diagnostic::new(source, offset, msg);
+        match file {
+            Some(file) => diag.with_file(file),
+            None => diag,
+        }
+    };
+    let max = with_options(|o| o.limits.get(Limit::InputSize));
+    if source.len() > max {
+        let msg = limit_message(Limit::InputSize, max);
+        return Err(UclError::LimitExceeded(Limit::InputSize, locate(0, msg)));
+    }
+
+    let saved = ERROR.with(|e| mem::take(&mut *e.borrow_mut()));
+    let priority = PRIORITY.with(Cell::get);
+    let result = ucl::document(source);
+    PRIORITY.with(|p| p.set(priority));
+    let failure = ERROR.with(|e| mem::replace(&mut *e.borrow_mut(), saved)).into_iter().next();
+
+    match (failure, result) {
+        (_, Ok(v)) => Ok(v),
+        (Some(Failure::Error(err)), _) => Err(err),
+        (Some(Failure::At(offset, msg)), _) => Err(UclError::Parse(locate(offset, msg))),
+        (Some(Failure::Duplicate(dup)), _) => Err(UclError::DuplicateKey {
+            first: Box::new(locate(dup.first, format!("`{}` first defined here", dup.key))),
+            second: Box::new(locate(dup.second, format!("duplicate key `{}`", dup.key))),
+            key: dup.key,
+        }),
+        (Some(Failure::Limit(limit, offset, msg)), _) => Err(UclError::LimitExceeded(limit, locate(offset, msg))),
+        (None, Err(err)) => {
+            let diag = Diagnostic::from_parse_error(err, source);
+            Err(UclError::Parse(match file {
+                Some(file) => diag.with_file(file),
+                None => diag,
+            }))
+        },
+    }
+}
+
+/// Returns the priority of values parsed now.
+pub fn priority() -> u32 {
+    PRIORITY.with(Cell::get)
+}
+
+/// Sets the priority of the values that follow, from the digits of a
+/// `.priority` macro at `offset`.
+pub fn set_priority(digits: &str, offset: usize) -> Result<(), &'static str> {
+    let n = parse_priority(digits, offset)?;
+    PRIORITY.with(|p| p.set(n));
+    Ok(())
+}
+
+/// Reads the digits of a priority at `offset`.
+pub fn parse_priority(digits: &str, offset: usize) -> Result<u32, &'static str> {
+    digits.parse().map_err(|_| {
+        fail_at(offset, format!("priority `{}` is out of range", digits));
+        "priority out of range"
+    })
+}
+
+/// Runs `f` with values parsed at `priority`.
+pub fn with_priority<T, F: FnOnce() -> T>(priority: u32, f: F) -> T {
+    let saved = PRIORITY.with(|p| p.replace(priority));
+    let rv = f();
+    PRIORITY.with(|p| p.set(saved));
+    rv
+}
+
+/// Fails if `n` is over `limit`, blaming `offset`.
+pub fn check_limit(limit: Limit, n: usize, offset: usize) -> Result<(), &'static str> {
+    let max = with_options(|o| o.limits.get(limit));
+    if n <= max {
+        return Ok(());
+    }
+    park(Failure::Limit(limit, offset, limit_message(limit, max)));
+    Err("limit exceeded")
+}
+
+/// Fails if the key or string `s` at `offset` is over the length limit.
+pub fn check_length(s: String, offset: usize) -> Result<String, &'static str> {
+    check_limit(Limit::StringLength, s.len(), offset).map(|_| s)
+}
+
+/// Describes input that goes over `max` of `limit`.
+pub fn limit_message(limit: Limit, max: usize) -> String {
+    match limit {
+        Limit::Depth => format!("arrays and objects nest deeper than {} levels", max),
+        Limit::InputSize => format!("input is larger than {} bytes", max),
+        Limit::StringLength => format!("string is longer than {} bytes", max),
+        Limit::Keys => format!("object has more than {} keys", max),
+        Limit::ArrayLength => format!("array has more than {} items", max),
+    }
+}
+
+/// Opens the array or object whose opening bracket is at `offset`.
+pub fn enter(offset: usize) -> Result<(), &'static str> {
+    let depth = DEPTH.with(Cell::get) + 1;
+    check_limit(Limit::Depth, depth, offset)?;
+    DEPTH.with(|d| d.set(depth));
+    Ok(())
+}
+
+/// Closes the array or object opened last.
+pub fn leave() {
+    DEPTH.with(|d| d.set(d.get().saturating_sub(1)));
+}
+
+/// Parks `err` as the outcome of the parse, should it fail, unless an
+/// earlier error was parked already.
+pub fn fail(err: UclError) {
+    park(Failure::Error(err));
+}
+
+/// Parks a parse error at `offset` of the source being parsed.
+pub fn fail_at<T: Into<String>>(offset: usize, message: T) {
+    park(Failure::At(offset, message.into()));
+}
+
+/// Parks a key repeated against the duplicate-key policy.
+pub fn fail_duplicate(dup: Duplicate) {
+    park(Failure::Duplicate(dup));
+}
+
+fn park(failure: Failure) {
+    ERROR.with(|e| e.borrow_mut().push(failure));
+}
+
+/// Drops the errors parked between `start` and `end`, which the grammar has
+/// just matched: they came from alternatives it backtracked out of.
+pub fn discard(start: usize, end: usize) {
+    ERROR.with(|e| e.borrow_mut().retain(|f| !f.offset().is_some_and(|o| o >= start && o < end)));
+}
@@ -0,0 +1,223 @@
+//! Paths to values inside a document, such as `site.api.permissions[1].role`.
+//!
+//! Object keys are joined with `.` and array indexes are written as `[n]`.
+//! Keys made of anything but letters, digits, `_` and `-` are double quoted,
+//! with `"` and `\` escaped by a backslash. The empty path is the document
+//! itself.
+//!
+//! Values can also be reached by JSON Pointer (RFC 6901), such as
+//! `/site/api/permissions/1/role`, where `~1` and `~0` stand for `/` and `~`
+//! in a key.
+
+use super::Value;
+use super::node::{Kind, Node};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Appends an object key to a path.
+pub fn push_key(path: &mut String, key: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    if !key.is_empty() && key.chars().all(is_plain) {
+        path.push_str(key);
+        return;
+    }
+    path.push('"');
+    for c in key.chars() {
+        if c == '"' || c == '\\' {
+            path.push('\\');
+        }
+        path.push(c);
+    }
+    path.push('"');
+}
+
+/// Appends an array index to a path.
+pub fn push_index(path: &mut String, idx: usize) {
+    path.push('[');
+    path.push_str(&idx.to_string());
+    path.push(']');
+}
+
+fn is_plain(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Splits a path into its segments, or returns `None` if it is malformed.
+pub fn parse(path: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '[' => {
+                chars.next();
+                let mut digits = String::new();
+                loop {
+                    match chars.next()? {
+                        ']' => break,
+                        c => digits.push(c),
+                    }
+                }
+                if !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                segments.push(Segment::Index(digits.parse().ok()?));
+                continue;
+            },
+            '.' if !segments.is_empty() => { chars.next(); },
+            _ if !segments.is_empty() => return None,
+            _ => (),
+        }
+
+        let mut key = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => key.push(chars.next()?),
+                    c => key.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if !is_plain(c) {
+                    break;
+                }
+                key.push(c);
+                chars.next();
+            }
+            if key.is_empty() {
+                return None;
+            }
+        }
+        segments.push(Segment::Key(key));
+    }
+    Some(segments)
+}
+
+/// Finds the node at `path` below `root`.
+pub fn find<'a>(root: &'a Node, path: &[Segment]) -> Option<&'a Node> {
+    path.iter().try_fold(root, |node, segment| match (&node.kind, segment) {
+        (Kind::Object(obj), Segment::Key(k)) => obj.get(k),
+        (Kind::Array(items), &Segment::Index(i)) => items.get(i),
+        _ => None,
+    })
+}
+
+/// Finds the value at `path` below `root`.
+pub fn lookup<'a>(root: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(root, |v, segment| match (v, segment) {
+        (Value::Object(obj), Segment::Key(k)) => obj.get(k),
+        (Value::Array(items), &Segment::Index(i)) => items.get(i),
+        _ => None,
+    })
+}
+
+/// Finds the value at `path` below `root`, for modification.
+pub fn lookup_mut<'a>(root: &'a mut Value, path: &[Segment]) -> Option<&'a mut Value> {
+    path.iter().try_fold(root, |v, segment| match (v, segment) {
+        (Value::Object(obj), Segment::Key(k)) => obj.get_mut(k),
+        (Value::Array(items), &Segment::Index(i)) => items.get_mut(i),
+        _ => None,
+    })
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens, or returns
+/// `None` if it is malformed.
+pub fn parse_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    pointer.strip_prefix('/')?.split('/').map(|token| {
+        let mut rv = String::with_capacity(token.len());
+        let mut chars = token.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '~' => rv.push(match chars.next()? {
+                    '0' => '~',
+                    '1' => '/',
+                    _ => return None,
+                }),
+                c => rv.push(c),
+            }
+        }
+        Some(rv)
+    }).collect()
+}
+
+/// Finds the value at the pointer `tokens` below `root`. A token selects an
+/// object key or, written without leading zeros, an array index.
+pub fn lookup_pointer<'a>(root: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(root, |v, token| match v {
+        Value::Object(obj) => obj.get(token),
+        Value::Array(items) => items.get(array_index(token)?),
+        _ => None,
+    })
+}
+
+/// Finds the value at the pointer `tokens` below `root`, for modification.
+pub fn lookup_pointer_mut<'a>(root: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(root, |v, token| match v {
+        Value::Object(obj) => obj.get_mut(token),
+        Value::Array(items) => items.get_mut(array_index(token)?),
+        _ => None,
+    })
+}
+
+fn array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path() {
+        let mut path = String::new();
+        push_key(&mut path, "site");
+        push_key(&mut path, "www.example.com");
+        push_index(&mut path, 2);
+        push_key(&mut path, "say \"hi\"");
+        push_key(&mut path, "");
+        assert_eq!(path, r#"site."www.example.com"[2]."say \"hi\""."""#);
+        assert_eq!(parse(&path), Some(vec![
+            Segment::Key("site".to_owned()),
+            Segment::Key("www.example.com".to_owned()),
+            Segment::Index(2),
+            Segment::Key("say \"hi\"".to_owned()),
+            Segment::Key("".to_owned()),
+        ]));
+
+        assert_eq!(parse(""), Some(vec![]));
+        assert_eq!(parse("[0][1].a"), Some(vec![Segment::Index(0), Segment::Index(1), Segment::Key("a".to_owned())]));
+        for bad in &["a..b", ".a", "a.", "a[x]", "a[1", "a[+1]", "a\"b\"", "\"a"] {
+            assert_eq!(parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!(parse_pointer(""), Some(vec![]));
+        assert_eq!(parse_pointer("/"), Some(vec!["".to_owned()]));
+        assert_eq!(parse_pointer("/a~1b/m~0n/~01/0"), Some(vec![
+            "a/b".to_owned(), "m~n".to_owned(), "~1".to_owned(), "0".to_owned(),
+        ]));
+        for bad in &["a", "/a~", "/a~2"] {
+            assert_eq!(parse_pointer(bad), None, "{}", bad);
+        }
+
+        for (token, idx) in &[("0", Some(0)), ("12", Some(12)), ("01", None), ("", None), ("-", None), ("+1", None)] {
+            assert_eq!(array_index(token), *idx, "{}", token);
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use std::fmt;
+use std::io;
+
+use serde::ser::{self, Impossible, Serialize, SerializeMap as _, SerializeSeq as _};
+
+use super::{emit, Array, Object, UclError, Value};
+
+impl ser::Error for UclError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        UclError::Message(msg.to_string())
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Value::Number(n) => serializer.serialize_i64(n),
+            Value::Float(n) | Value::Time(n) => serializer.serialize_f64(n),
+            Value::Size(n) => serializer.serialize_i64(n),
+            Value::String(ref s) => serializer.serialize_str(s),
+            Value::Boolean(b) => serializer.serialize_bool(b),
+            Value::Array(ref items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for v in items {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            },
+            Value::Object(ref obj) => {
+                let mut map = serializer.serialize_map(Some(obj.len()))?;
+                for (k, v) in obj {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            },
+            Value::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+/// Serializes `value` as a UCL document.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, UclError> {
+    emit::to_string(&to_value(value)?)
+}
+
+/// Serializes `value` as a UCL document into `writer`.
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(mut writer: W, value: &T) -> Result<(), UclError> {
+    writer.write_all(to_string(value)?.as_bytes())?;
+    Ok(())
+}
+
+/// Converts `value` into a `Value` tree.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, UclError> {
+    value.serialize(Serializer)
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = UclError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVariant<SerializeVec>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, UclError> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, UclError> {
+        Ok(Value::Number(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, UclError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, UclError> {
+        if v > i64::MAX as u64 {
+            return Err(ser::Error::custom(format!("integer {} is out of range", v)));
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, UclError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, UclError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, UclError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, UclError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, UclError> {
+        Ok(Value::Array(v.iter().map(|&b| Value::Number(b as i64)).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value, UclError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, UclError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, UclError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, UclError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str)
+                              -> Result<Value, UclError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+                                                       -> Result<Value, UclError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        variant: &'static str, value: &T)
+                                                        -> Result<Value, UclError> {
+        let mut obj = Object::new();
+        obj.insert(variant.to_owned(), to_value(value)?);
+        Ok(Value::Object(obj))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, UclError> {
+        Ok(SerializeVec { items: Array::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, UclError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec, UclError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize)
+                               -> Result<SerializeVariant<SerializeVec>, UclError> {
+        Ok(SerializeVariant { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, UclError> {
+        Ok(SerializeMap { obj: Object::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, UclError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize)
+                                -> Result<SerializeVariant<SerializeMap>, UclError> {
+        Ok(SerializeVariant { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+struct SerializeVec {
+    items: Array,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), UclError> {
+        self.items.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        Ok(Value::Array(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), UclError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), UclError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeMap {
+    obj: Object,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), UclError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), UclError> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.obj.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        Ok(Value::Object(self.obj))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), UclError> {
+        self.obj.insert(key.to_owned(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+// Enum variants with data are written as a single-key object, the same shape
+// the deserializer accepts.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeVec> {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), UclError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        let mut obj = Object::new();
+        obj.insert(self.variant.to_owned(), ser::SerializeSeq::end(self.inner)?);
+        Ok(Value::Object(obj))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Value;
+    type Error = UclError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), UclError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, UclError> {
+        let mut obj = Object::new();
+        obj.insert(self.variant.to_owned(), ser::SerializeMap::end(self.inner)?);
+        Ok(Value::Object(obj))
+    }
+}
+
+struct KeySerializer;
+
+fn key_must_be_a_string() -> UclError {
+    UclError::Message("object key must be a string".to_owned())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = UclError;
+
+    type SerializeSeq = Impossible<String, UclError>;
+    type SerializeTuple = Impossible<String, UclError>;
+    type SerializeTupleStruct = Impossible<String, UclError>;
+    type SerializeTupleVariant = Impossible<String, UclError>;
+    type SerializeMap = Impossible<String, UclError>;
+    type SerializeStruct = Impossible<String, UclError>;
+    type SerializeStructVariant = Impossible<String, UclError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, UclError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, UclError> {
+        Ok(v.to_owned())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, UclError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str)
+                              -> Result<String, UclError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+                                                       -> Result<String, UclError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T)
+                                                        -> Result<String, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize)
+                              -> Result<Self::SerializeTupleStruct, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                               -> Result<Self::SerializeTupleVariant, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, UclError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                                -> Result<Self::SerializeStructVariant, UclError> {
+        Err(key_must_be_a_string())
+    }
+}
@@ -0,0 +1,126 @@
+//! Source locations of parsed keys and values.
+//!
+//! ```
+//! let (v, spans) = ucl::parse_spanned("site {\n    port = 8080;\n}\n").unwrap();
+//! assert_eq!(v["site"]["port"], ucl::Value::from(8080));
+//!
+//! let span = spans.value("site.port").unwrap();
+//! assert_eq!((span.line, span.column), (2, 12));
+//! assert_eq!((span.start, span.end), (18, 22));
+//! ```
+
+use indexmap::IndexMap;
+
+use super::node::{Kind, Node, Range};
+use super::path::{push_index, push_key};
+
+/// A range of the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset just past the last character.
+    pub end: usize,
+    /// 1-based line of `start`.
+    pub line: usize,
+    /// 1-based column of `start`, counted in characters.
+    pub column: usize,
+}
+
+/// The spans of every key and value of a document, keyed by path.
+///
+/// A path joins object keys with `.` and array indexes with `[n]`, as in
+/// `site.api.permissions[1].role`. Keys made of anything but letters, digits,
+/// `_` and `-` are double quoted, with `"` and `\` escaped by a backslash. The
+/// document itself has the empty path.
+///
+/// Every element of an implicit array, one collected from a repeated key,
+/// has its own key span. So does each part of a `site "www" { ... }` header:
+/// `site` gets the span of `site` and `site.www` the span of `"www"`. Values
+/// brought in by `.include` are placed at the span of the macro.
+#[derive(Debug, Clone, Default)]
+pub struct Spans {
+    values: IndexMap<String, Span>,
+    keys: IndexMap<String, Span>,
+}
+
+impl Spans {
+    pub(crate) fn new(root: &Node, source: &str) -> Spans {
+        let mut builder = Builder {
+            lines: line_starts(source),
+            source,
+            spans: Spans::default(),
+        };
+        builder.walk(root, String::new());
+        builder.spans
+    }
+
+    /// Returns the span of the value at `path`.
+    pub fn value(&self, path: &str) -> Option<Span> {
+        self.values.get(path).cloned()
+    }
+
+    /// Returns the span of the key the value at `path` was written under.
+    pub fn key(&self, path: &str) -> Option<Span> {
+        self.keys.get(path).cloned()
+    }
+
+    /// Iterates over the paths and spans of all values in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Span)> + '_ {
+        self.values.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    lines: Vec<usize>,
+    spans: Spans,
+}
+
+impl<'a> Builder<'a> {
+    fn span(&self, range: Range) -> Span {
+        let line = match self.lines.binary_search(&range.0) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        Span {
+            start: range.0,
+            end: range.1,
+            line: line + 1,
+            column: self.source[self.lines[line]..range.0].chars().count() + 1,
+        }
+    }
+
+    fn walk(&mut self, node: &Node, path: String) {
+        let span = self.span(node.span);
+        self.spans.values.insert(path.clone(), span);
+        if let Some(key) = node.key {
+            let span = self.span(key);
+            self.spans.keys.insert(path.clone(), span);
+        }
+
+        match node.kind {
+            Kind::Scalar(_) => (),
+            Kind::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let mut path = path.clone();
+                    push_index(&mut path, i);
+                    self.walk(item, path);
+                }
+            },
+            Kind::Object(ref obj) => {
+                for (k, v) in obj {
+                    let mut path = path.clone();
+                    push_key(&mut path, k);
+                    self.walk(v, path);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Expansion of `$NAME` and `${NAME}` references in strings.
+
+use std::env;
+use std::path::Path;
+
+use super::include;
+use super::parser::{self, Options, UnknownVariable};
+
+/// Expands the variable references in a string value found at `offset` of
+/// the source, if the parser has expansion on.
+pub fn expand(s: String, offset: usize) -> Result<String, &'static str> {
+    if !s.contains('$') || !parser::with_options(|options| options.expand_variables) {
+        return Ok(s);
+    }
+    let file = include::current_file();
+    parser::with_options(|options| {
+        substitute(&s, options.unknown_variables, |name| lookup(options, file.as_deref(), name))
+    }).map_err(|name| {
+        parser::fail_at(offset, format!("unknown variable `{}`", name));
+        "unknown variable"
+    })
+}
+
+fn lookup(options: &Options, file: Option<&Path>, name: &str) -> Option<String> {
+    if let Some(v) = options.variables.get(name) {
+        return Some(v.clone());
+    }
+    match (name, file) {
+        ("FILENAME", Some(file)) => return Some(file.to_string_lossy().into_owned()),
+        ("CURDIR", Some(file)) => return file.parent().map(|dir| dir.to_string_lossy().into_owned()),
+        _ => (),
+    }
+    if options.env.iter().any(|v| v == name) {
+        return env::var(name).ok();
+    }
+    None
+}
+
+/// Replaces every reference in `s` with what `lookup` finds for it. Fails
+/// with the name of the first unknown variable if `policy` says so.
+fn substitute<F: Fn(&str) -> Option<String>>(s: &str, policy: UnknownVariable, lookup: F)
+                                             -> Result<String, String> {
+    let mut rv = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        rv.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if rest.starts_with('$') {
+            rv.push('$');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let (name, len) = if rest.starts_with('{') {
+            match rest.find('}') {
+                Some(end) => (&rest[1..end], end + 1),
+                None => ("", 0),
+            }
+        } else {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            (&rest[..end], end)
+        };
+        if !is_name(name) {
+            rv.push('$');
+            continue;
+        }
+
+        match lookup(name) {
+            Some(v) => rv.push_str(&v),
+            None => match policy {
+                UnknownVariable::Keep => {
+                    rv.push('$');
+                    rv.push_str(&rest[..len]);
+                },
+                UnknownVariable::Empty => (),
+                UnknownVariable::Error => return Err(name.to_owned()),
+            },
+        }
+        rest = &rest[len..];
+    }
+    rv.push_str(rest);
+    Ok(rv)
+}
+
+fn is_name(s: &str) -> bool {
+    match s.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        let lookup = |name: &str| match name {
+            "A" => Some("1".to_owned()),
+            "LONG_NAME" => Some("x$A".to_owned()),
+            _ => None,
+        };
+        let expand = |s, policy| substitute(s, policy, lookup);
+
+        assert_eq!(expand("$A ${A}b $LONG_NAME-$$A", UnknownVariable::Error), Ok("1 1b x$A-$A".to_owned()));
+        assert_eq!(expand("$ $1 ${} ${A $", UnknownVariable::Error), Ok("$ $1 ${} ${A $".to_owned()));
+        assert_eq!(expand("[$B|${B}|$Ab]", UnknownVariable::Keep), Ok("[$B|${B}|$Ab]".to_owned()));
+        assert_eq!(expand("[$B|${B}|$Ab]", UnknownVariable::Empty), Ok("[||]".to_owned()));
+        assert_eq!(expand("$A$Ab", UnknownVariable::Error), Err("Ab".to_owned()));
+    }
+}
@@ -0,0 +1,184 @@
+#![cfg(feature = "serde")]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate ucl;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Config {
+    version: f64,
+    this_must_appear: bool,
+    general: General,
+    site: Site,
+    #[serde(rename = "500_html")]
+    html_500: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct General {
+    user: String,
+    daemon: bool,
+    fork: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Site {
+    log_rotate: f64,
+    bind: Vec<String>,
+    api: Api,
+    www: Www,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Api {
+    timeout: f64,
+    max_recv_size: usize,
+    permissions: Vec<Permission>,
+    upstream: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Permission {
+    user: String,
+    role: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Www {
+    base_dir: String,
+    timeout: f64,
+    index: Vec<String>,
+    file_types: Vec<String>,
+}
+
+#[test]
+fn test_from_file() {
+    let cfg: Config = ucl::from_file("tests/test.ucl").unwrap();
+    assert_eq!(cfg.version, 1.0);
+    assert!(cfg.this_must_appear);
+    assert_eq!(cfg.general, General { user: "nobody".to_owned(), daemon: true, fork: 4 });
+    assert_eq!(cfg.site.log_rotate, (7 * 24 * 60 * 60) as f64);
+    assert_eq!(cfg.site.bind, vec![":80", ":443"]);
+    assert_eq!(cfg.site.api.timeout, 1.0);
+    assert_eq!(cfg.site.api.max_recv_size, 25 * 1024 * 1024);
+    assert_eq!(cfg.site.api.permissions[1], Permission { user: "guest".to_owned(), role: "".to_owned() });
+    assert_eq!(cfg.site.api.upstream, None);
+    assert_eq!(cfg.site.www.base_dir, "/var/www");
+    assert_eq!(cfg.site.www.timeout, 0.1);
+    assert_eq!(cfg.site.www.index, vec!["index.html", "index.htm"]);
+    assert_eq!(cfg.site.www.file_types, vec![".html", ".css", ".js"]);
+    assert!(cfg.html_500.starts_with("<h1>500"));
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    Memory,
+    File(String),
+    Redis { host: String, port: u16 },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Storage {
+    primary: Backend,
+    fallback: Vec<Backend>,
+    index: Vec<String>,
+    weights: HashMap<String, f64>,
+    ratio: f32,
+}
+
+#[test]
+fn test_from_str() {
+    let storage: Storage = ucl::from_str(r#"
+        primary {
+            redis {
+                host = localhost;
+                port = 6379;
+            }
+        }
+        fallback = [memory, { file = "/tmp/cache"; }];
+        index = index.html;
+        weights {
+            a = 0.5;
+            b = 1;
+        }
+        ratio = 1;
+    "#).unwrap();
+    assert_eq!(storage.primary, Backend::Redis { host: "localhost".to_owned(), port: 6379 });
+    assert_eq!(storage.fallback, vec![Backend::Memory, Backend::File("/tmp/cache".to_owned())]);
+    assert_eq!(storage.index, vec!["index.html"]);
+    assert_eq!(storage.weights["a"], 0.5);
+    assert_eq!(storage.weights["b"], 1.0);
+    assert_eq!(storage.ratio, 1.0);
+}
+
+#[test]
+fn test_from_value_error() {
+    let v = ucl::parse("user = nobody; daemon = true; fork = many;").unwrap();
+    let err = ucl::from_value::<General>(&v).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid type: string "many", expected u32"#);
+
+    let v = ucl::parse("fork = 70000;").unwrap();
+    let err = ucl::from_value::<HashMap<String, u16>>(&v).unwrap_err();
+    assert_eq!(err.to_string(), "invalid value: integer `70000`, expected u16");
+}
+
+#[test]
+fn test_to_string_round_trip() {
+    let cfg: Config = ucl::from_file("tests/test.ucl").unwrap();
+    let text = ucl::to_string(&cfg).unwrap();
+    assert_eq!(ucl::parse(&text).unwrap(), ucl::to_value(&cfg).unwrap());
+
+    let mut weights = HashMap::new();
+    weights.insert("a b".to_owned(), 0.5);
+    weights.insert("".to_owned(), -2.0);
+    let storage = Storage {
+        primary: Backend::Redis { host: "10".to_owned(), port: 6379 },
+        fallback: vec![Backend::Memory, Backend::File("multi\nline\nEOD\n".to_owned())],
+        index: vec!["true".to_owned(), "".to_owned(), "1s".to_owned(), "say \"hi\"".to_owned()],
+        weights,
+        ratio: 3.0,
+    };
+    let text = ucl::to_string(&storage).unwrap();
+    assert_eq!(ucl::parse(&text).unwrap(), ucl::to_value(&storage).unwrap());
+    assert_eq!(ucl::from_str::<Storage>(&text).unwrap(), storage);
+
+    let mut weights = HashMap::new();
+    weights.insert("99999999999999999999".to_owned(), 1.0);
+    let storage = Storage {
+        primary: Backend::Redis { host: "99999999999999999999".to_owned(), port: 6379 },
+        fallback: vec![Backend::File("1e400".to_owned())],
+        index: vec!["-9223372036854775809".to_owned(), "9999999999gb".to_owned(), "-.5".to_owned()],
+        weights,
+        ratio: 3.0,
+    };
+    let text = ucl::to_string(&storage).unwrap();
+    assert_eq!(ucl::from_str::<Storage>(&text).unwrap(), storage);
+}
+
+#[test]
+fn test_to_string_format() {
+    #[derive(Serialize)]
+    struct Doc {
+        section: Section,
+    }
+
+    #[derive(Serialize)]
+    struct Section {
+        banner: String,
+    }
+
+    let doc = Doc { section: Section { banner: "<h1>Hello</h1>\n".to_owned() } };
+    let mut out = Vec::new();
+    ucl::to_writer(&mut out, &doc).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "section {\n    banner = <<EOD\n<h1>Hello</h1>\n\nEOD\n;\n}\n");
+
+    let err = ucl::to_string(&vec![1, 2]).unwrap_err();
+    assert_eq!(err.to_string(), "the root of a UCL document must be an object");
+
+    let mut m = HashMap::new();
+    m.insert("ratio", f64::NAN);
+    assert!(ucl::to_string(&m).is_err());
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 extern crate ucl;
 
 use ucl::*;
@@ -34,4 +36,206 @@ fn test_parse_file() {
     assert_eq!(cfg["site"]["www"]["file_types"][2].unwrap::<String>(), ".js".to_owned());
 
     assert_eq!(cfg["500_html"].unwrap::<String>(), "<h1>500 Internal Server Error</h1>\n\n<p>Please contact to ...<at>...</p>");
+
+    let bind: Vec<std::net::SocketAddr> = cfg.try_get("site.bind").unwrap();
+    assert_eq!(bind, vec!["0.0.0.0:80".parse().unwrap(), "0.0.0.0:443".parse().unwrap()]);
+    let base_dir: std::path::PathBuf = cfg.try_get("site.www.base_dir").unwrap();
+    assert_eq!(base_dir, std::path::Path::new("/var/www"));
+}
+
+#[test]
+fn test_parse_file_include() {
+    let cfg = parse_file("tests/include/main.ucl").unwrap();
+    assert_eq!(cfg["general"]["user"].unwrap::<String>(), "nobody".to_owned());
+    assert_eq!(cfg["logging"]["level"].unwrap::<String>(), "info".to_owned());
+    assert_eq!(cfg["site"]["api"]["timeout"].unwrap::<f64>(), 1_f64);
+    assert_eq!(cfg["vhost"]["www"]["base_dir"].unwrap::<String>(), "/var/www".to_owned());
+    assert_eq!(cfg["vhost"]["www"]["index"][0].unwrap::<String>(), "index.html".to_owned());
+    assert_eq!(cfg["vhost"]["www"]["index"][1].unwrap::<String>(), "index.htm".to_owned());
+}
+
+#[test]
+fn test_parse_file_include_cycle() {
+    match parse_file("tests/include/cycle/a.ucl") {
+        Err(UclError::IncludeCycle(path)) => assert!(path.ends_with("cycle/a.ucl")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_file_include_missing() {
+    match parse(r#".include "tests/include/missing.ucl""#) {
+        Err(UclError::Io(_)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(parse(r#".include "tests/include/missing/*.ucl""#).unwrap(), Value::from(vec![] as Vec<(Key, Value)>));
+}
+
+#[test]
+fn test_parse_file_diagnostic() {
+    let diag = match parse_file("tests/broken.ucl") {
+        Err(UclError::Parse(diag)) => diag,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(diag.file, Some("tests/broken.ucl".into()));
+    assert_eq!((diag.line, diag.column), (2, 18));
+    assert_eq!(diag.to_string(), "tests/broken.ucl:2:18: unexpected `5`, expected one of `,`, `;`");
+    assert!(diag.render().contains("2 |     timeout = 1s 5s\n  |                  ^\n"));
+}
+
+#[test]
+fn test_parse_file_key_order() {
+    let cfg = parse_file("tests/test.ucl").unwrap();
+    let keys = |v: &Value| v.unwrap::<Object>().keys().cloned().collect::<Vec<_>>();
+    assert_eq!(keys(&cfg), vec!["version", "this_must_appear", "general", "site", "500_html"]);
+    assert_eq!(keys(&cfg["general"]), vec!["user", "daemon", "fork"]);
+    assert_eq!(keys(&cfg["site"]), vec!["log_rotate", "bind", "api", "www"]);
+    assert_eq!(keys(&cfg["site"]["www"]), vec!["base_dir", "timeout", "index", "file_types"]);
+}
+
+#[test]
+fn test_parse_file_spanned() {
+    let source = std::fs::read_to_string("tests/test.ucl").unwrap();
+    let (cfg, spans) = parse_file_spanned("tests/test.ucl").unwrap();
+    assert_eq!(cfg, parse_file("tests/test.ucl").unwrap());
+    let text = |span: Span| &source[span.start..span.end];
+
+    let role = spans.value("site.api.permissions[1].role").unwrap();
+    assert_eq!(text(role), "\"\"");
+    assert_eq!((role.line, role.column), (32, 16));
+    assert_eq!(text(spans.key("site.api.permissions[1].role").unwrap()), "role");
+    assert!(text(spans.value("site.api.permissions[1]").unwrap()).starts_with("{\n        user = guest;"));
+
+    let www = spans.key("site.www").unwrap();
+    assert_eq!(text(www), "\"www\"");
+    assert_eq!((www.line, www.column), (37, 6));
+    assert_eq!(text(spans.key("site").unwrap()), "site");
+    assert_eq!(spans.key("site").unwrap().line, 16);
+    assert_eq!(text(spans.value("site.www.index[1]").unwrap()), "index.htm");
+    assert_eq!(spans.key("site.www.index[1]").unwrap().line, 41);
+
+    assert_eq!(text(spans.value("site.log_rotate").unwrap()), "7d");
+    assert_eq!(text(spans.value("site.bind[0]").unwrap()), ":80");
+    assert!(spans.value("site.api.upstream").is_none());
+    assert_eq!(spans.iter().next().map(|(path, _)| path), Some(""));
+}
+
+#[test]
+fn test_document_parse_file() {
+    let source = std::fs::read_to_string("tests/test.ucl").unwrap();
+    let mut doc = Document::parse_file("tests/test.ucl").unwrap();
+    assert_eq!(doc.to_string(), source);
+    assert_eq!(doc.value(), &parse_file("tests/test.ucl").unwrap());
+
+    doc.set("general.fork", 8).unwrap();
+    assert_eq!(doc.to_string(), source.replace("fork = 4;", "fork = 8;"));
+    assert_eq!(doc.get("general.fork").unwrap().unwrap::<i64>(), 8);
+
+    let mut doc = Document::parse_file("tests/include/main.ucl").unwrap();
+    doc.set("general.user", "root").unwrap();
+    assert_eq!(doc.get("logging.level").unwrap().unwrap::<String>(), "info".to_owned());
+    assert!(doc.set("logging.level", "debug").is_err());
+}
+
+#[test]
+fn test_parse_file_variables() {
+    let path = std::fs::canonicalize("tests/vars.ucl").unwrap();
+    let dir = path.parent().unwrap().to_str().unwrap().to_owned();
+
+    let cfg = parse_file("tests/vars.ucl").unwrap();
+    assert_eq!(cfg["self"].unwrap::<String>(), "$FILENAME");
+    assert_eq!(cfg["greeting"].unwrap::<String>(), "hello, $USER_NAME");
+
+    let cfg = Parser::new().expand_variables(true).parse_file("tests/vars.ucl").unwrap();
+    assert_eq!(cfg["self"].unwrap::<String>(), path.to_str().unwrap());
+    assert_eq!(cfg["data"].unwrap::<String>(), dir.clone() + "/data");
+    assert_eq!(cfg["home"].unwrap::<String>(), "$HOME");
+    assert_eq!(cfg["greeting"].unwrap::<String>(), "hello, $USER_NAME");
+
+    std::env::set_var("UCL_TEST_HOME", "/home/test");
+    let cfg = Parser::new()
+        .variable("USER_NAME", "test")
+        .variable("HOME", "$UCL_TEST_HOME")
+        .env("UCL_TEST_HOME")
+        .parse_file("tests/vars.ucl")
+        .unwrap();
+    assert_eq!(cfg["home"].unwrap::<String>(), "$UCL_TEST_HOME");
+    assert_eq!(cfg["greeting"].unwrap::<String>(), "hello, test");
+    let cfg = Parser::new().env("UCL_TEST_HOME").parse("home = $UCL_TEST_HOME; other = ${PATH};").unwrap();
+    assert_eq!(cfg["home"].unwrap::<String>(), "/home/test");
+    assert_eq!(cfg["other"].unwrap::<String>(), "${PATH}");
+
+    let cfg = Parser::new().unknown_variables(UnknownVariable::Empty).parse_file("tests/vars.ucl").unwrap();
+    assert_eq!(cfg["home"].unwrap::<String>(), "");
+
+    match Parser::new().unknown_variables(UnknownVariable::Error).parse_file("tests/vars.ucl") {
+        Err(UclError::Parse(diag)) => assert_eq!(diag.to_string(), "tests/vars.ucl:4:8: unknown variable `HOME`"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_file_priority() {
+    let cfg = parse_file("tests/priority/main.ucl").unwrap();
+    assert_eq!(cfg["general"]["fork"].unwrap::<i64>(), 8);
+    assert_eq!(cfg["general"]["user"].unwrap::<String>(), "nobody".to_owned());
+    assert_eq!(cfg["listen"].unwrap::<String>(), ":8080".to_owned());
+    assert_eq!(cfg["modules"], Value::from(vec![Value::from("core"), Value::from("ssl")]));
+}
+
+#[test]
+fn test_parse_file_limits() {
+    let cfg = Parser::new().max_input_size(usize::MAX).parse_file("tests/limits/utf8.ucl").unwrap();
+    assert_eq!(cfg["motd"].unwrap::<String>(), "h\u{e9}llo");
+    let cfg = Parser::new().max_input_size(usize::MAX).parse_file("tests/include/main.ucl").unwrap();
+    assert_eq!(cfg, parse_file("tests/include/main.ucl").unwrap());
+    assert!(Parser::new().max_input_size(17).parse_file("tests/limits/utf8.ucl").is_ok());
+
+    // Ten bytes end inside the `\u{e9}`.
+    for &max in &[16, 10] {
+        match Parser::new().max_input_size(max).parse_file("tests/limits/utf8.ucl") {
+            Err(UclError::LimitExceeded(Limit::InputSize, diag)) => {
+                assert_eq!(diag.to_string(), format!("tests/limits/utf8.ucl:1:1: input is larger than {} bytes", max));
+            },
+            other => panic!("unexpected result for {}: {:?}", max, other),
+        }
+    }
+}
+
+// Every file under tests/fuzz is malformed input that once crashed the
+// parser or came close to; each must fail with a located error instead.
+#[test]
+fn test_parse_file_fuzz_corpus() {
+    // Syntax errors are matched up to the list of what was expected.
+    let expected = [
+        ("array_overflow.ucl", 1, 16, "number `9223372036854775807k` is out of range"),
+        ("binary_overflow.ucl", 1, 8, "number `9999999999gb` is out of range"),
+        ("deep_json.ucl", 1, 769, "arrays and objects nest deeper than 128 levels"),
+        ("deep_nesting.ucl", 1, 133, "arrays and objects nest deeper than 128 levels"),
+        ("hex_overflow.ucl", 1, 5, "number `0xffffffffffffffffff` is out of range"),
+        ("int_overflow.ucl", 1, 5, "number `99999999999999999999` is out of range"),
+        ("int_underflow.ucl", 1, 5, "number `-9223372036854775809` is out of range"),
+        ("json_overflow.ucl", 1, 7, "number `18446744073709551616` is out of range"),
+        ("lone_surrogate.ucl", 1, 6, "lone surrogate `\\ud800`"),
+        ("mismatched_heredoc.ucl", 3, 1, "unexpected end of input"),
+        ("priority_overflow.ucl", 1, 11, "priority `99999999999` is out of range"),
+        ("short_unicode_escape.ucl", 1, 6, "invalid unicode escape, expected four hex digits"),
+        ("si_overflow.ucl", 1, 8, "number `9999999999g` is out of range"),
+        ("truncated.ucl", 1, 21, "unexpected end of input"),
+        ("unterminated_heredoc.ucl", 3, 1, "unexpected end of input"),
+    ];
+    let mut files: Vec<_> = std::fs::read_dir("tests/fuzz").unwrap().map(|e| e.unwrap().path()).collect();
+    files.sort();
+    assert_eq!(files.len(), expected.len());
+    for (file, &(name, line, column, message)) in files.iter().zip(expected.iter()) {
+        assert_eq!(file.file_name().unwrap(), name);
+        match parse_file(file) {
+            Err(UclError::Parse(diag)) | Err(UclError::LimitExceeded(_, diag)) => {
+                assert_eq!(diag.file.as_ref(), Some(file));
+                assert_eq!((diag.line, diag.column), (line, column), "{}", diag);
+                assert!(diag.message == message || diag.message.starts_with(&format!("{}, expected", message)), "{}", diag);
+            },
+            other => panic!("unexpected result for {}: {:?}", file.display(), other),
+        }
+    }
 }